@@ -0,0 +1,37 @@
+import { test, assert } from 'test-anywhere';
+import { LinoEnv } from '../src/lino-env.mjs';
+import { readdirSync, readFileSync } from 'node:fs';
+import { join } from 'node:path';
+import { fileURLToPath } from 'node:url';
+import { isDeepStrictEqual } from 'node:util';
+
+// Fixtures shared with the Rust package, see conformance/README.md
+const FIXTURES = fileURLToPath(new URL('../../conformance/', import.meta.url));
+
+// Fixtures this package does not read as expected yet. Their tests fail once
+// they pass, so that they are removed from here and from the README.
+const KNOWN_DIFFERENCES = new Set(['crlf', 'nested_block']);
+
+const names = readdirSync(FIXTURES)
+  .filter((file) => file.endsWith('.lenv'))
+  .map((file) => file.slice(0, -'.lenv'.length))
+  .sort();
+
+for (const name of names) {
+  test(`should read conformance fixture ${name}`, () => {
+    const expected = JSON.parse(
+      readFileSync(join(FIXTURES, `${name}.json`), 'utf-8')
+    );
+    const env = new LinoEnv(join(FIXTURES, `${name}.lenv`)).read();
+    const actual = env.keys().map((key) => [key, env.get(key)]);
+
+    if (KNOWN_DIFFERENCES.has(name)) {
+      assert.ok(
+        !isDeepStrictEqual(actual, expected),
+        `${name} is read as expected now; remove it from KNOWN_DIFFERENCES`
+      );
+    } else {
+      assert.deepEqual(actual, expected);
+    }
+  });
+}
@@ -0,0 +1,26 @@
+//! Reading arbitrary bytes must never panic, and every value read must be
+//! written back to the same value when that is possible.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lino_env::parser::{format_entry, is_lossless, parse_str, Event};
+use lino_env::LinoEnv;
+
+fuzz_target!(|data: &[u8]| {
+    let mut env = LinoEnv::new("fuzz.lenv");
+    if env.read_from(data).is_err() {
+        return;
+    }
+    for key in env.keys() {
+        let value = env.get(&key).unwrap_or_default();
+        if !is_lossless(&value) {
+            continue;
+        }
+        let events = parse_str(&format_entry("KEY", &value));
+        assert!(
+            matches!(events.as_slice(), [Event::Entry { value: read, .. }] if *read == value),
+            "{value:?} was read back as {events:?}"
+        );
+    }
+});
@@ -0,0 +1,217 @@
+//! `#[derive(LinoEnvConfig)]`.
+
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Lit, LitStr, Meta,
+    PathArguments, Type,
+};
+
+/// A field of the struct and the key it is read from.
+struct Field<'a> {
+    ident: &'a Ident,
+    /// The field type, or `T` for `Option<T>`.
+    ty: &'a Type,
+    optional: bool,
+    key: String,
+    default: Option<LitStr>,
+    description: Option<String>,
+}
+
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if path.qself.is_some() || segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+/// The schema type of a field, guessed from the name of its type.
+fn key_type(ty: &Type) -> TokenStream {
+    let name = match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+    match name.as_deref() {
+        Some(
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize",
+        ) => quote!(::lino_env::schema::KeyType::Int),
+        Some("bool") => quote!(::lino_env::schema::KeyType::Bool),
+        Some("Url") => quote!(::lino_env::schema::KeyType::Url),
+        _ => quote!(::lino_env::schema::KeyType::String),
+    }
+}
+
+/// The `///` comments of a field joined into one line.
+fn description(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) => Some(text.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+fn field(field: &syn::Field) -> syn::Result<Field<'_>> {
+    let ident = field
+        .ident
+        .as_ref()
+        .ok_or_else(|| syn::Error::new_spanned(field, "expected a named field"))?;
+    let mut key = None;
+    let mut default = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("lenv"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("key") {
+                let value: LitStr = meta.value()?.parse()?;
+                let text = value.value();
+                if text.trim().is_empty() || text.contains(": ") || text.contains('\n') {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "a key must be non-empty and cannot contain `: ` or a newline",
+                    ));
+                }
+                key = Some(text);
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                default = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown `lenv` attribute, expected `key` or `default`"))
+            }
+        })?;
+    }
+
+    let (ty, optional) = option_inner(&field.ty).map_or((&field.ty, false), |inner| (inner, true));
+    Ok(Field {
+        ident,
+        ty,
+        optional,
+        key: key.unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_uppercase()),
+        default,
+        description: description(&field.attrs),
+    })
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "`LinoEnvConfig` can only be derived for structs with named fields",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &data.fields,
+            "`LinoEnvConfig` can only be derived for structs with named fields",
+        ));
+    };
+    let fields = named
+        .named
+        .iter()
+        .map(field)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let reads = fields.iter().map(|field| {
+        let Field { ident, ty, key, .. } = field;
+        let default = field.default.as_ref().map_or_else(
+            || quote!(::core::option::Option::None),
+            |default| quote!(::core::option::Option::Some(#default)),
+        );
+        if field.optional {
+            quote!(#ident: ::lino_env::config::parse_optional::<#ty>(env, #key, #default)?)
+        } else {
+            quote!(#ident: ::lino_env::config::parse_value::<#ty>(env, #key, #default)?)
+        }
+    });
+
+    let writes = fields.iter().map(|field| {
+        let Field { ident, key, .. } = field;
+        let comment = field
+            .description
+            .as_ref()
+            .map(|description| quote!(env.set_comment(#key, #description);));
+        if field.optional {
+            quote! {
+                if let ::core::option::Option::Some(value) = &self.#ident {
+                    env.set(#key, &::std::string::ToString::to_string(value));
+                    #comment
+                }
+            }
+        } else {
+            quote! {
+                env.set(#key, &::std::string::ToString::to_string(&self.#ident));
+                #comment
+            }
+        }
+    });
+
+    let specs = fields.iter().map(|field| {
+        let key = &field.key;
+        let key_type = key_type(field.ty);
+        let required = !field.optional && field.default.is_none();
+        let default = field.default.as_ref().map(
+            |default| quote!(spec.default = ::core::option::Option::Some(::std::string::ToString::to_string(#default));),
+        );
+        let description = field.description.as_ref().map(|description| {
+            quote!(spec.description = ::core::option::Option::Some(::std::string::ToString::to_string(#description));)
+        });
+        quote! {
+            let mut spec = ::lino_env::schema::KeySpec::new(#key);
+            spec.key_type = #key_type;
+            spec.required = #required;
+            #default
+            #description
+            schema.add(spec);
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::lino_env::config::LinoEnvConfig for #name #ty_generics #where_clause {
+            fn from_lino_env(
+                env: &::lino_env::LinoEnv,
+            ) -> ::core::result::Result<Self, ::lino_env::config::ConfigError> {
+                ::core::result::Result::Ok(Self {
+                    #(#reads,)*
+                })
+            }
+
+            fn to_lino_env(&self) -> ::lino_env::LinoEnv {
+                let mut env = ::lino_env::LinoEnv::new(".lenv");
+                #(#writes)*
+                env
+            }
+
+            fn schema() -> ::lino_env::schema::Schema {
+                let mut schema = ::lino_env::schema::Schema::new();
+                #(#specs)*
+                schema
+            }
+        }
+    })
+}
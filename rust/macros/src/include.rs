@@ -0,0 +1,113 @@
+//! `include_lenv!`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, LitStr, Token};
+
+use crate::parser::{parse_str, Event};
+
+/// `"path"` or `"path" as LinoEnv`.
+pub struct Input {
+    path: LitStr,
+    as_env: bool,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let as_env = if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            let target: Ident = input.parse()?;
+            if target != "LinoEnv" {
+                return Err(syn::Error::new(target.span(), "expected `LinoEnv`"));
+            }
+            true
+        } else {
+            false
+        };
+        Ok(Self { path, as_env })
+    }
+}
+
+/// Entries of `source` in file order; a duplicated key keeps its first
+/// position and its last value, as in `LinoEnv`.
+///
+/// Lines the parser cannot interpret are errors here, reported as
+/// `FILE:LINE: message`.
+pub fn entries(file: &str, source: &str) -> Result<Vec<(String, String)>, String> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut positions = HashMap::new();
+    for event in parse_str(source) {
+        match event {
+            Event::Entry { key, value, .. } => {
+                if let Some(&index) = positions.get(&key) {
+                    entries[index] = (key, value);
+                } else {
+                    positions.insert(key.clone(), entries.len());
+                    entries.push((key, value));
+                }
+            }
+            Event::Error { kind, span, .. } => {
+                return Err(format!("{file}:{}: {kind}", span.start_line));
+            }
+            Event::Comment { .. } | Event::BlankLine { .. } => {}
+        }
+    }
+    Ok(entries)
+}
+
+pub fn expand(input: &Input) -> syn::Result<TokenStream> {
+    let error = |message: String| syn::Error::new(input.path.span(), message);
+    let relative = input.path.value();
+    let root = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("`CARGO_MANIFEST_DIR` is not set".to_string()))?;
+    let path = Path::new(&root).join(&relative);
+    let source =
+        std::fs::read_to_string(&path).map_err(|e| error(format!("{}: {e}", path.display())))?;
+    let entries = entries(&relative, &source).map_err(error)?;
+
+    // Rebuild when the file changes.
+    let full_path = LitStr::new(&path.to_string_lossy(), Span::call_site());
+    let keys = entries.iter().map(|(key, _)| key);
+    let values = entries.iter().map(|(_, value)| value);
+    Ok(if input.as_env {
+        quote! {{
+            const _: &[u8] = ::core::include_bytes!(#full_path);
+            let mut env = ::lino_env::LinoEnv::new(#relative);
+            #(env.set(#keys, #values);)*
+            env
+        }}
+    } else {
+        quote! {{
+            const _: &[u8] = ::core::include_bytes!(#full_path);
+            &[#((#keys, #values)),*]
+        }}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries() {
+        let entries = entries("a.lenv", "# c\nB: 1\nA: \"x\ny\"\nB: 2\n").unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("B".to_string(), "2".to_string()),
+                ("A".to_string(), "x\ny".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_point_to_the_line() {
+        let error = entries("a.lenv", "A: 1\n\nbroken\n").unwrap_err();
+        assert_eq!(error, "a.lenv:3: line has no `: ` separator");
+    }
+}
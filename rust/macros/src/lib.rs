@@ -0,0 +1,46 @@
+//! Procedural macros for the `lino-env` crate.
+//!
+//! Use them through `lino-env` with the `derive` and `embed` features
+//! rather than depending on this crate directly.
+
+mod derive;
+mod include;
+// A copy of the parser of `lino-env`, so that `include_lenv!` reads files
+// exactly as `LinoEnv::read` does. A test of `lino-env` and CI check that it
+// matches `src/parser.rs`; edit both together.
+#[allow(dead_code, clippy::redundant_pub_crate)]
+mod parser;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implement `lino_env::config::LinoEnvConfig` for a struct with named
+/// fields.
+///
+/// Each field is read from the key given by `#[lenv(key = "...")]`, or the
+/// field name in uppercase. `#[lenv(default = "...")]` supplies a value for a
+/// missing key, `Option` fields may be missing, and `///` comments become
+/// schema descriptions.
+#[proc_macro_derive(LinoEnvConfig, attributes(lenv))]
+pub fn derive_lino_env_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Embed a `.lenv` file, parsed at compile time.
+///
+/// The path is relative to the directory containing the crate's
+/// `Cargo.toml`. `include_lenv!("defaults.lenv")` expands to a
+/// `&'static [(&'static str, &'static str)]` of the entries in file order;
+/// `include_lenv!("defaults.lenv" as LinoEnv)` expands to a `LinoEnv` with
+/// those entries. Lines that do not parse are compile errors naming the
+/// file and line.
+#[proc_macro]
+pub fn include_lenv(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as include::Input);
+    include::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
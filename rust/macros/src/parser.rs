@@ -0,0 +1,597 @@
+//! Streaming event parser for `.lenv` files.
+//!
+//! The parser reads from any [`BufRead`] one line at a time and yields
+//! [`Event`]s, so files larger than memory can be processed and tooling
+//! (linters, formatters, editors) can be built on the same rules that
+//! [`LinoEnv::read`](crate::LinoEnv::read) uses.
+//!
+//! Each line is one of:
+//!
+//! - blank, or a comment starting with `#` after optional whitespace;
+//! - `key: value`, split at the first `: `, with the key trimmed. A value
+//!   opening a quote that is not closed on the same line continues up to
+//!   the next occurrence of that quote on a later line;
+//! - `key:` followed by lines indented deeper than it: one entry whose value
+//!   is those lines with their common indentation removed (blank lines in
+//!   between are kept). The indented lines belong to the value even if they
+//!   look like `key: value` entries themselves, so they define no keys of
+//!   their own;
+//! - anything else, including `key:` without indented lines, is an
+//!   [`Event::Error`].
+//!
+//! # Examples
+//!
+//! ```
+//! use lino_env::parser::{Event, Parser};
+//!
+//! let input = "# tokens\nGITHUB_TOKEN: gh_abc\n\nbroken line\n";
+//! let events: Vec<Event> = Parser::new(input.as_bytes())
+//!     .collect::<std::io::Result<_>>()
+//!     .unwrap();
+//!
+//! assert!(matches!(events[0], Event::Comment { .. }));
+//! assert!(matches!(&events[1], Event::Entry { key, value, .. } if key == "GITHUB_TOKEN" && value == "gh_abc"));
+//! assert!(matches!(events[2], Event::BlankLine { .. }));
+//! assert!(matches!(events[3], Event::Error { .. }));
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// Location of an event in the input.
+///
+/// Lines are 1-based and inclusive; byte offsets are 0-based and exclude
+/// the line terminator of the last line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    /// First line of the event.
+    pub start_line: usize,
+    /// Last line of the event (differs from `start_line` for multi-line values).
+    pub end_line: usize,
+    /// Byte offset of the first character of the event.
+    pub start: usize,
+    /// Byte offset just past the last character of the event.
+    pub end: usize,
+}
+
+/// Kind of a recoverable parse error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    /// A non-empty, non-comment line without the `: ` separator.
+    MissingSeparator,
+    /// A value opened a multi-line quote that is never closed.
+    UnterminatedQuote,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "line has no `: ` separator"),
+            Self::UnterminatedQuote => write!(f, "quoted value is never closed"),
+        }
+    }
+}
+
+/// A single item produced by the [`Parser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A `#` comment line; `indent` is the whitespace before the `#` and
+    /// `text` is everything after it.
+    Comment {
+        indent: String,
+        text: String,
+        span: Span,
+    },
+    /// An empty or whitespace-only line.
+    BlankLine { span: Span },
+    /// A `key: value` entry, possibly spanning several lines.
+    ///
+    /// A `key:` line followed by more deeply indented lines is a nested
+    /// entry; its value is the indented block with the common indentation
+    /// removed.
+    Entry {
+        key: String,
+        value: String,
+        span: Span,
+    },
+    /// A line the parser could not interpret; parsing continues after it.
+    Error {
+        kind: ParseErrorKind,
+        text: String,
+        span: Span,
+    },
+}
+
+impl Event {
+    /// The location of this event in the input.
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        match self {
+            Self::Comment { span, .. }
+            | Self::BlankLine { span }
+            | Self::Entry { span, .. }
+            | Self::Error { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Line {
+    text: String,
+    number: usize,
+    start: usize,
+}
+
+impl Line {
+    fn span(&self) -> Span {
+        Span {
+            start_line: self.number,
+            end_line: self.number,
+            start: self.start,
+            end: self.start + self.text.len(),
+        }
+    }
+}
+
+/// Pull parser yielding [`Event`]s from a [`BufRead`].
+///
+/// Only the current entry is held in memory, except for a multi-line quoted
+/// value that is never closed: its lines are buffered until the end of the
+/// input and then re-parsed as ordinary lines, matching the behavior of
+/// [`LinoEnv::read`](crate::LinoEnv::read).
+#[derive(Debug)]
+pub struct Parser<R> {
+    reader: R,
+    line_number: usize,
+    offset: usize,
+    pending: VecDeque<Line>,
+    queued: VecDeque<Event>,
+}
+
+impl<R: BufRead> Parser<R> {
+    /// Create a parser over `reader`.
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+            offset: 0,
+            pending: VecDeque::new(),
+            queued: VecDeque::new(),
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<Line>> {
+        if let Some(line) = self.pending.pop_front() {
+            return Ok(Some(line));
+        }
+
+        let mut text = String::new();
+        let read = self.reader.read_line(&mut text)?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        let start = self.offset;
+        self.offset += read;
+        self.line_number += 1;
+        if text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+
+        Ok(Some(Line {
+            text,
+            number: self.line_number,
+            start,
+        }))
+    }
+
+    fn parse_entry(&mut self, line: &Line, separator_index: usize) -> io::Result<Event> {
+        let key = line.text[..separator_index].trim().to_string();
+        let value = &line.text[separator_index + 2..]; // Don't trim value to preserve spaces
+        let mut span = line.span();
+
+        let trimmed_value = value.trim_start();
+        let quote = trimmed_value.chars().next();
+        let Some(quote) = quote.filter(|quote| matches!(quote, '"' | '\'')) else {
+            return Ok(entry(key, value.to_string(), span));
+        };
+
+        let first_part = &trimmed_value[quote.len_utf8()..];
+
+        // Preserve existing single-line quoted value behavior.
+        if first_part.contains(quote) {
+            return Ok(entry(key, value.to_string(), span));
+        }
+
+        let mut parts = vec![first_part.to_string()];
+        let mut consumed = Vec::new();
+        while let Some(next) = self.next_line()? {
+            if let Some(closing_quote_index) = next.text.find(quote) {
+                parts.push(next.text[..closing_quote_index].to_string());
+                span.end_line = next.number;
+                span.end = next.start + next.text.len();
+                return Ok(entry(key, parts.join("\n"), span));
+            }
+
+            parts.push(next.text.clone());
+            consumed.push(next);
+        }
+
+        // Never closed: keep the raw first-line value and re-parse the rest.
+        self.pending.extend(consumed);
+        self.queued.push_back(entry(key, value.to_string(), span));
+        Ok(Event::Error {
+            kind: ParseErrorKind::UnterminatedQuote,
+            text: line.text.clone(),
+            span,
+        })
+    }
+
+    fn parse_block(&mut self, line: Line, key: String) -> io::Result<Event> {
+        let indent = indentation(&line.text);
+        let mut children: Vec<Line> = Vec::new();
+        let mut lookahead = Vec::new();
+        while let Some(next) = self.next_line()? {
+            let blank = next.text.trim().is_empty();
+            let nested = indentation(&next.text) > indent;
+            lookahead.push(next);
+            if blank {
+                continue;
+            }
+            if !nested {
+                break;
+            }
+            children.append(&mut lookahead);
+        }
+        // Lines after the block (and blank lines before them) are parsed normally.
+        for next in lookahead.into_iter().rev() {
+            self.pending.push_front(next);
+        }
+
+        let Some(last) = children.last() else {
+            return Ok(Event::Error {
+                kind: ParseErrorKind::MissingSeparator,
+                span: line.span(),
+                text: line.text,
+            });
+        };
+        let mut span = line.span();
+        span.end_line = last.number;
+        span.end = last.start + last.text.len();
+
+        let common = children
+            .iter()
+            .filter(|child| !child.text.trim().is_empty())
+            .map(|child| indentation(&child.text))
+            .min()
+            .unwrap_or(0);
+        let value = children
+            .iter()
+            .map(|child| child.text.get(common..).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(entry(key, value, span))
+    }
+
+    fn parse_next(&mut self) -> io::Result<Option<Event>> {
+        if let Some(event) = self.queued.pop_front() {
+            return Ok(Some(event));
+        }
+
+        let Some(line) = self.next_line()? else {
+            return Ok(None);
+        };
+
+        let trimmed = line.text.trim();
+        if trimmed.is_empty() {
+            return Ok(Some(Event::BlankLine { span: line.span() }));
+        }
+        if let Some(text) = trimmed.strip_prefix('#') {
+            return Ok(Some(Event::Comment {
+                indent: line.text[..line.text.len() - line.text.trim_start().len()].to_string(),
+                text: text.to_string(),
+                span: line.span(),
+            }));
+        }
+
+        // Parse line with `: ` separator
+        if let Some(separator_index) = line.text.find(": ") {
+            return self.parse_entry(&line, separator_index).map(Some);
+        }
+        if let Some(key) = trimmed.strip_suffix(':').map(str::trim_end) {
+            if !key.is_empty() {
+                let key = key.to_string();
+                return self.parse_block(line, key).map(Some);
+            }
+        }
+
+        Ok(Some(Event::Error {
+            kind: ParseErrorKind::MissingSeparator,
+            span: line.span(),
+            text: line.text,
+        }))
+    }
+}
+
+/// Width in bytes of the leading spaces and tabs of `line`.
+pub(crate) fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+const fn entry(key: String, value: String, span: Span) -> Event {
+    Event::Entry { key, value, span }
+}
+
+impl<R: BufRead> Iterator for Parser<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parse_next().transpose()
+    }
+}
+
+/// Parse an in-memory string into a list of events.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::parser::{parse_str, Event};
+///
+/// let events = parse_str("A: 1\n");
+/// assert!(matches!(&events[0], Event::Entry { key, .. } if key == "A"));
+/// ```
+#[must_use]
+pub fn parse_str(input: &str) -> Vec<Event> {
+    Parser::new(input.as_bytes())
+        .collect::<io::Result<_>>()
+        .expect("reading from a string cannot fail")
+}
+
+/// How [`format_entry`] writes a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// `key: value` on one line.
+    Plain,
+    /// `key:` followed by the value's lines indented by two spaces.
+    Block,
+    /// `key: "value"` with a quote that does not occur in the value.
+    Quoted(char),
+}
+
+/// The lossless style for `value`, if there is one.
+fn style(value: &str) -> Option<Style> {
+    let lines: Vec<&str> = value.split('\n').collect();
+    // The parser strips a `\r` before each line break.
+    if lines.iter().any(|line| line.ends_with('\r')) {
+        return None;
+    }
+    let is_blank = |line: &&str| line.trim().is_empty();
+    let block = !lines.first().is_some_and(is_blank)
+        && !lines.last().is_some_and(is_blank)
+        && lines
+            .iter()
+            .any(|line| !is_blank(line) && indentation(line) == 0);
+    let quote = ['"', '\'']
+        .into_iter()
+        .find(|quote| !value.contains(*quote));
+
+    if lines.len() == 1 && !opens_quote(value) {
+        Some(Style::Plain)
+    } else if block {
+        Some(Style::Block)
+    } else if lines.len() > 1 {
+        quote.map(Style::Quoted)
+    } else {
+        None
+    }
+}
+
+/// Whether `value` after `key: ` would start a multi-line quoted value.
+fn opens_quote(value: &str) -> bool {
+    let value = value.trim_start();
+    value
+        .chars()
+        .next()
+        .filter(|quote| matches!(quote, '"' | '\''))
+        .is_some_and(|quote| !value[1..].contains(quote))
+}
+
+/// Whether [`format_entry`] writes `value` so that the parser reads it back
+/// unchanged.
+///
+/// A few values have no such form: a line ending in a carriage return, a
+/// single line with leading whitespace and an unmatched quote, and several
+/// lines containing both quote characters with blank first or last lines or
+/// no unindented line.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::parser::is_lossless;
+///
+/// assert!(is_lossless("say \"hi\"\nand 'bye'"));
+/// assert!(!is_lossless("\n\"'"));
+/// ```
+#[must_use]
+pub fn is_lossless(value: &str) -> bool {
+    style(value).is_some()
+}
+
+/// Whether [`format_entry`] writes `key` so that the parser reads it back
+/// unchanged.
+///
+/// Keys are read up to the first `: `, trimmed, and a line starting with `#`
+/// is a comment, so a key must be non-empty, on one line, without
+/// surrounding whitespace, a leading `#` or a `: `.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::parser::is_lossless_key;
+///
+/// assert!(is_lossless_key("API_URL"));
+/// assert!(!is_lossless_key("# note"));
+/// assert!(!is_lossless_key("a: b"));
+/// ```
+#[must_use]
+pub fn is_lossless_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.trim() == key
+        && !key.starts_with('#')
+        && !key.contains(": ")
+        && !key.contains(['\n', '\r'])
+}
+
+/// Format a single entry so that the parser reads back exactly `value`.
+///
+/// Single-line values are written as `key: value`. Multi-line values, and
+/// single lines starting with an unmatched quote, are written as an
+/// indented block when that is lossless (no leading or trailing blank lines
+/// and at least one unindented line), otherwise between quotes that do not
+/// occur in the value. Values that are not [lossless](is_lossless) are
+/// written as closely as possible.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::parser::format_entry;
+///
+/// assert_eq!(format_entry("A", "1"), "A: 1\n");
+/// assert_eq!(format_entry("A", "x: 1\ny: 2"), "A:\n  x: 1\n  y: 2\n");
+/// assert_eq!(format_entry("A", "\nx\n"), "A: \"\nx\n\"\n");
+/// assert_eq!(format_entry("A", "\"open"), "A:\n  \"open\n");
+/// ```
+#[must_use]
+pub fn format_entry(key: &str, value: &str) -> String {
+    // Without a lossless style, a block at least keeps other entries intact.
+    let plain = !value.contains('\n') && !opens_quote(value);
+    let fallback = if plain { Style::Plain } else { Style::Block };
+    match style(value).unwrap_or(fallback) {
+        Style::Plain => format!("{key}: {value}\n"),
+        Style::Quoted(quote) => format!("{key}: {quote}{value}{quote}\n"),
+        Style::Block => value
+            .split('\n')
+            .fold(format!("{key}:\n"), |mut text, line| {
+                if !line.is_empty() {
+                    text.push_str("  ");
+                    text.push_str(line);
+                }
+                text.push('\n');
+                text
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(events: &[Event]) -> Vec<(&str, &str)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_event_kinds() {
+        let events = parse_str("# comment\n\nA: 1\nnot an entry\n");
+        assert_eq!(
+            events[0],
+            Event::Comment {
+                indent: String::new(),
+                text: " comment".to_string(),
+                span: Span {
+                    start_line: 1,
+                    end_line: 1,
+                    start: 0,
+                    end: 9
+                }
+            }
+        );
+        assert!(matches!(events[1], Event::BlankLine { .. }));
+        assert_eq!(entries(&events), vec![("A", "1")]);
+        assert!(matches!(
+            events[3],
+            Event::Error {
+                kind: ParseErrorKind::MissingSeparator,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_multiline_span() {
+        let events = parse_str("A: \"x\ny\"\nB: 2\n");
+        assert_eq!(entries(&events), vec![("A", "x\ny"), ("B", "2")]);
+        let span = events[0].span();
+        assert_eq!((span.start_line, span.end_line), (1, 2));
+        assert_eq!((span.start, span.end), (0, 8));
+        assert_eq!(events[1].span().start, 9);
+    }
+
+    #[test]
+    fn test_unterminated_quote_reparses_following_lines() {
+        let events = parse_str("A: \"open\nB: 2\n");
+        assert!(matches!(
+            events[0],
+            Event::Error {
+                kind: ParseErrorKind::UnterminatedQuote,
+                ..
+            }
+        ));
+        assert_eq!(entries(&events), vec![("A", "\"open"), ("B", "2")]);
+        assert_eq!(events[2].span().start_line, 2);
+    }
+
+    #[test]
+    fn test_nested_block() {
+        let events = parse_str("A:\n  x: 1\n\n  y:\n    - 2\n\nB: 3\nC:\nD: 4\n");
+        assert_eq!(
+            entries(&events),
+            vec![("A", "x: 1\n\ny:\n  - 2"), ("B", "3"), ("D", "4")]
+        );
+        assert_eq!(
+            (events[0].span().start_line, events[0].span().end_line),
+            (1, 5)
+        );
+        assert!(matches!(events[1], Event::BlankLine { .. }));
+        assert!(matches!(
+            events[3],
+            Event::Error {
+                kind: ParseErrorKind::MissingSeparator,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_format_entry_round_trip() {
+        for value in [
+            "",
+            "plain",
+            "x: 1\n  y: 2",
+            "\nleading blank",
+            "  all\n  indented",
+            "has \"double\"\nand 'single' quotes",
+            "line\n\nwith blank",
+        ] {
+            let events = parse_str(&format_entry("K", value));
+            assert_eq!(entries(&events), vec![("K", value)], "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let events = parse_str("A: 1\r\nB: 2\r\n");
+        assert_eq!(entries(&events), vec![("A", "1"), ("B", "2")]);
+        assert_eq!(events[1].span().start, 6);
+    }
+}
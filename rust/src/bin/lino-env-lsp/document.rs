@@ -0,0 +1,140 @@
+//! Open documents and conversion between byte offsets and LSP positions.
+//!
+//! LSP positions count UTF-16 code units within a line.
+
+use lino_env::parser::{parse_str, Event, Span};
+use serde_json::{json, Value};
+
+/// The text of an open document.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+/// An entry of a document with the location of its key.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    pub span: Span,
+    /// Byte offset of the first character of the key.
+    pub key_start: usize,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    /// The LSP position of byte `offset`.
+    pub fn position(&self, offset: usize) -> Value {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = self.text[start..offset].encode_utf16().count();
+        json!({ "line": line, "character": character })
+    }
+
+    /// The byte offset of an LSP position, clamped to the line.
+    pub fn offset(&self, position: &Value) -> usize {
+        let field = |name| {
+            position[name]
+                .as_u64()
+                .and_then(|n| usize::try_from(n).ok())
+                .unwrap_or_default()
+        };
+        let Some(&start) = self.line_starts.get(field("line")) else {
+            return self.text.len();
+        };
+        let line = self.text[start..].split('\n').next().unwrap_or_default();
+        let mut units = 0;
+        for (index, c) in line.char_indices() {
+            if units >= field("character") {
+                return start + index;
+            }
+            units += c.len_utf16();
+        }
+        start + line.len()
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> Value {
+        json!({ "start": self.position(start), "end": self.position(end) })
+    }
+
+    /// The range of the whole document.
+    pub fn full_range(&self) -> Value {
+        self.range(0, self.text.len())
+    }
+
+    /// Entries in file order.
+    pub fn entries(&self) -> Vec<Entry> {
+        parse_str(&self.text)
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Entry { key, value, span } => {
+                    let line = &self.text[span.start..];
+                    let key_start = span.start + line.find(key.as_str()).unwrap_or_default();
+                    Some(Entry {
+                        key,
+                        value,
+                        span,
+                        key_start,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The entry at an LSP position.
+    pub fn entry_at(&self, position: &Value) -> Option<Entry> {
+        let offset = self.offset(position);
+        self.entries()
+            .into_iter()
+            .find(|entry| entry.span.start <= offset && offset <= entry.span.end)
+    }
+}
+
+impl Entry {
+    /// Byte range of the key.
+    pub fn key_range(&self) -> (usize, usize) {
+        (self.key_start, self.key_start + self.key.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let document = Document::new("A: 1\nÉ𝄞: x\n".to_string());
+        assert_eq!(document.position(0), json!({"line": 0, "character": 0}));
+        assert_eq!(document.position(5), json!({"line": 1, "character": 0}));
+        // `É` is one UTF-16 unit (two bytes), `𝄞` two units (four bytes).
+        assert_eq!(document.position(11), json!({"line": 1, "character": 3}));
+        assert_eq!(document.offset(&json!({"line": 1, "character": 3})), 11);
+        assert_eq!(document.offset(&json!({"line": 0, "character": 99})), 4);
+        assert_eq!(document.offset(&json!({"line": 9, "character": 0})), 15);
+    }
+
+    #[test]
+    fn test_entry_at() {
+        let document = Document::new("# c\n  PORT: 80\nB: \"x\ny\"\n".to_string());
+        let entry = document
+            .entry_at(&json!({"line": 3, "character": 1}))
+            .unwrap();
+        assert_eq!(entry.key, "B");
+        let entry = document
+            .entry_at(&json!({"line": 1, "character": 4}))
+            .unwrap();
+        assert_eq!(entry.key_range(), (6, 10));
+        assert!(document
+            .entry_at(&json!({"line": 0, "character": 0}))
+            .is_none());
+    }
+}
@@ -0,0 +1,32 @@
+//! `lino-env-lsp` - language server for `.lenv` files.
+//!
+//! Speaks the Language Server Protocol over stdin and stdout and provides
+//! diagnostics (parse errors, lint findings and schema violations), hover
+//! with the schema description of a key, completion of keys from the
+//! schema, go-to-definition across layered files (`.lenv`, `.lenv.local`,
+//! ...) and document formatting.
+//!
+//! Exit codes: `0` after a `shutdown` request, `1` if the client exits
+//! without one, `2` on I/O errors.
+
+mod document;
+mod server;
+mod transport;
+mod workspace;
+
+use std::io;
+use std::process::ExitCode;
+
+use server::Server;
+
+fn main() -> ExitCode {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    match Server::new().run(&mut input, &mut output) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("lino-env-lsp: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,565 @@
+//! Request and notification handlers.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use lino_env::lint::Severity;
+use lino_env::parser::{parse_str, Event};
+use lino_env::schema::{KeySpec, Schema, Violation};
+use lino_env::LinoEnv;
+use serde_json::{json, Value};
+
+use crate::document::Document;
+use crate::transport::{read_message, write_message};
+use crate::workspace::{layers, lint_config, path_to_uri, schema_path, uri_to_path};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+/// Diagnostic severities of the protocol.
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+
+/// Completion item kind `Property`.
+const PROPERTY: u8 = 10;
+
+type RequestResult = Result<Value, (i64, String)>;
+
+/// State of a language server session.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit: Option<ExitCode>,
+}
+
+fn invalid_params() -> (i64, String) {
+    (INVALID_PARAMS, "unknown document".to_string())
+}
+
+fn notification(method: &str, params: &Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn describe(spec: &KeySpec) -> String {
+    let mut text = format!("**{}** `{}`", spec.name, spec.key_type);
+    if spec.required {
+        text.push_str(" (required)");
+    }
+    if let Some(description) = &spec.description {
+        text.push_str("\n\n");
+        text.push_str(description);
+    }
+    if let Some(default) = &spec.default {
+        let _ = write!(text, "\n\nDefault: `{default}`");
+    }
+    text
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve messages from `input` until `exit` or the end of the input.
+    pub fn run<R: BufRead, W: Write>(
+        &mut self,
+        input: &mut R,
+        output: &mut W,
+    ) -> io::Result<ExitCode> {
+        while let Some(message) = read_message(input)? {
+            for reply in self.handle(&message) {
+                write_message(output, &reply)?;
+            }
+            if let Some(code) = self.exit {
+                return Ok(code);
+            }
+        }
+        Ok(if self.shutdown {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+
+    /// Handle one message and return the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            return Vec::new();
+        };
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params);
+        };
+        let reply = match self.request(method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        vec![reply]
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> RequestResult {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "the server is shut down".to_string()));
+        }
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "definitionProvider": true,
+                    "documentFormattingProvider": true,
+                },
+                "serverInfo": { "name": "lino-env-lsp", "version": lino_env::VERSION },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/formatting" => self.formatting(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit = Some(if self.shutdown {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                });
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents
+                    .insert(uri.to_string(), Document::new(text.to_string()));
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    self.documents
+                        .insert(uri.to_string(), Document::new(text.to_string()));
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                let params = json!({ "uri": uri, "diagnostics": [] });
+                let mut replies = vec![notification("textDocument/publishDiagnostics", &params)];
+                replies.extend(self.publish_all());
+                return replies;
+            }
+            "textDocument/didSave" => {}
+            _ => return Vec::new(),
+        }
+        self.publish_all()
+    }
+
+    /// Diagnostics of every open document, since a change to one layer
+    /// affects the others.
+    fn publish_all(&self) -> Vec<Value> {
+        let mut uris: Vec<&String> = self.documents.keys().collect();
+        uris.sort();
+        uris.into_iter()
+            .map(|uri| {
+                let params = json!({ "uri": uri, "diagnostics": self.diagnostics(uri) });
+                notification("textDocument/publishDiagnostics", &params)
+            })
+            .collect()
+    }
+
+    fn document(&self, params: &Value) -> Result<&Document, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents.get(uri).ok_or_else(invalid_params)
+    }
+
+    /// The text of `path`, from the editor if it is open.
+    fn text(&self, path: &Path) -> Option<String> {
+        self.documents
+            .iter()
+            .find(|(uri, _)| uri_to_path(uri).as_deref() == Some(path))
+            .map(|(_, document)| document.text.clone())
+            .or_else(|| fs::read_to_string(path).ok())
+    }
+
+    fn schema(params: &Value) -> Option<Schema> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        Schema::read(schema_path(&uri_to_path(uri)?)?).ok()
+    }
+
+    fn diagnostics(&self, uri: &str) -> Vec<Value> {
+        let Some(document) = self.documents.get(uri) else {
+            return Vec::new();
+        };
+        let path = uri_to_path(uri);
+        let config = path.as_deref().map(lint_config).unwrap_or_default();
+        let mut diagnostics: Vec<Value> = config
+            .lint(&document.text)
+            .into_iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": document.range(diagnostic.span.start, diagnostic.span.end),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => ERROR,
+                        Severity::Warning => WARNING,
+                    },
+                    "code": diagnostic.rule.id(),
+                    "source": "lino-env",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+
+        let Some(schema_file) = path.as_deref().and_then(schema_path) else {
+            return diagnostics;
+        };
+        let schema = match Schema::read(&schema_file) {
+            Ok(schema) => schema,
+            Err(error) => {
+                diagnostics.push(json!({
+                    "range": document.range(0, 0),
+                    "severity": ERROR,
+                    "code": "schema",
+                    "source": "lino-env",
+                    "message": format!("{}: {error}", schema_file.display()),
+                }));
+                return diagnostics;
+            }
+        };
+        diagnostics.extend(self.schema_diagnostics(&schema, document, path.as_deref()));
+        diagnostics
+    }
+
+    /// Values of `document` with the wrong type, and required keys missing
+    /// from every layer.
+    fn schema_diagnostics(
+        &self,
+        schema: &Schema,
+        document: &Document,
+        path: Option<&Path>,
+    ) -> Vec<Value> {
+        let mut own = LinoEnv::new("");
+        let mut stack = LinoEnv::new("");
+        let layers: Vec<PathBuf> = path.map(layers).unwrap_or_default();
+        for layer in &layers {
+            if let Some(text) = self.text(layer) {
+                for event in parse_str(&text) {
+                    if let Event::Entry { key, value, .. } = event {
+                        stack.set(&key, &value);
+                    }
+                }
+            }
+        }
+        let entries = document.entries();
+        for entry in &entries {
+            own.set(&entry.key, &entry.value);
+            stack.set(&entry.key, &entry.value);
+        }
+
+        let invalid = schema.validate(&own).into_iter().filter_map(|violation| {
+            let Violation::InvalidValue { key, expected } = violation else {
+                return None;
+            };
+            let entry = entries.iter().rev().find(|entry| entry.key == key)?;
+            let (start, end) = entry.key_range();
+            Some(json!({
+                "range": document.range(start, end),
+                "severity": ERROR,
+                "code": "schema",
+                "source": "lino-env",
+                "message": format!("`{key}` must be {expected}"),
+            }))
+        });
+        let missing = schema.validate(&stack).into_iter().filter_map(|violation| {
+            let Violation::MissingRequired { key } = violation else {
+                return None;
+            };
+            Some(json!({
+                "range": document.range(0, 0),
+                "severity": ERROR,
+                "code": "schema",
+                "source": "lino-env",
+                "message": format!("required key `{key}` is not set in any layer"),
+            }))
+        });
+        invalid.chain(missing).collect()
+    }
+
+    fn hover(&self, params: &Value) -> RequestResult {
+        let document = self.document(params)?;
+        let Some(entry) = document.entry_at(&params["position"]) else {
+            return Ok(Value::Null);
+        };
+        let Some(schema) = Self::schema(params) else {
+            return Ok(Value::Null);
+        };
+        let Some(spec) = schema.get(&entry.key) else {
+            return Ok(Value::Null);
+        };
+        let (start, end) = entry.key_range();
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": describe(spec) },
+            "range": document.range(start, end),
+        }))
+    }
+
+    fn completion(&self, params: &Value) -> RequestResult {
+        let document = self.document(params)?;
+        let offset = document.offset(&params["position"]);
+        let line_start = document.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        if document.text[line_start..offset].contains(": ") {
+            return Ok(json!([]));
+        }
+        let Some(schema) = Self::schema(params) else {
+            return Ok(json!([]));
+        };
+        let defined: Vec<String> = document.entries().into_iter().map(|e| e.key).collect();
+        let items: Vec<Value> = schema
+            .keys()
+            .iter()
+            .filter(|spec| !defined.contains(&spec.name))
+            .map(|spec| {
+                json!({
+                    "label": spec.name,
+                    "kind": PROPERTY,
+                    "detail": spec.key_type.to_string(),
+                    "documentation": { "kind": "markdown", "value": describe(spec) },
+                    "insertText": format!("{}: {}", spec.name, spec.default.as_deref().unwrap_or_default()),
+                })
+            })
+            .collect();
+        Ok(json!(items))
+    }
+
+    fn definition(&self, params: &Value) -> RequestResult {
+        let document = self.document(params)?;
+        let Some(entry) = document.entry_at(&params["position"]) else {
+            return Ok(Value::Null);
+        };
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(path) = uri_to_path(uri) else {
+            return Ok(Value::Null);
+        };
+        let mut locations = Vec::new();
+        for layer in layers(&path) {
+            let Some(text) = self.text(&layer) else {
+                continue;
+            };
+            let layer_document = Document::new(text);
+            for definition in layer_document.entries() {
+                if definition.key == entry.key {
+                    let (start, end) = definition.key_range();
+                    locations.push(json!({
+                        "uri": path_to_uri(&layer),
+                        "range": layer_document.range(start, end),
+                    }));
+                }
+            }
+        }
+        Ok(json!(locations))
+    }
+
+    fn formatting(&self, params: &Value) -> RequestResult {
+        let document = self.document(params)?;
+        let formatted =
+            lino_env::format(&document.text).map_err(|e| (REQUEST_FAILED, e.to_string()))?;
+        if formatted == document.text {
+            return Ok(json!([]));
+        }
+        Ok(json!([{ "range": document.full_range(), "newText": formatted }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempDir;
+
+    struct Workspace {
+        dir: TempDir,
+    }
+
+    impl Workspace {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let dir = TempDir::new().unwrap();
+            for (file, text) in files {
+                fs::write(dir.join(file), text).unwrap();
+            }
+            Self { dir }
+        }
+
+        fn uri(&self, file: &str) -> String {
+            path_to_uri(&self.dir.join(file))
+        }
+    }
+
+    const SCHEMA: &str = "\
+PORT
+  type: int
+  description: Port to listen on
+DATABASE_URL
+  type: url
+  required: true
+";
+
+    fn request(server: &mut Server, method: &str, params: &Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        server.handle(&message).remove(0)["result"].clone()
+    }
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "lenv", "version": 1, "text": text } },
+        }))
+    }
+
+    fn at(uri: &str, line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let workspace = Workspace::new(&[(".lenv.schema", SCHEMA)]);
+        let uri = workspace.uri(".lenv");
+        let mut server = Server::new();
+        let published = open(&mut server, &uri, "PORT: http\nbroken\n");
+
+        let diagnostics = &published[0]["params"]["diagnostics"];
+        let messages: Vec<&str> = diagnostics
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["message"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line has no `: ` separator and is ignored",
+                "`PORT` must be int",
+                "required key `DATABASE_URL` is not set in any layer",
+            ]
+        );
+        assert_eq!(
+            diagnostics[1]["range"],
+            json!({"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 4}})
+        );
+    }
+
+    #[test]
+    fn test_layers_share_required_keys() {
+        let workspace = Workspace::new(&[
+            (".lenv.schema", SCHEMA),
+            (".lenv", "DATABASE_URL: https://db\nPORT: 1\n"),
+        ]);
+        let uri = workspace.uri(".lenv.local");
+        let mut server = Server::new();
+        let published = open(&mut server, &uri, "PORT: 8080\n");
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+
+        let definitions = request(&mut server, "textDocument/definition", &at(&uri, 0, 1));
+        assert_eq!(
+            definitions,
+            json!([
+                {
+                    "uri": workspace.uri(".lenv"),
+                    "range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 4}},
+                },
+                {
+                    "uri": uri,
+                    "range": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 4}},
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_hover_and_completion() {
+        let workspace = Workspace::new(&[(".lenv.schema", SCHEMA)]);
+        let uri = workspace.uri(".lenv");
+        let mut server = Server::new();
+        open(&mut server, &uri, "PORT: 80\n\n");
+
+        let hover = request(&mut server, "textDocument/hover", &at(&uri, 0, 2));
+        assert_eq!(
+            hover["contents"]["value"],
+            "**PORT** `int`\n\nPort to listen on"
+        );
+        assert_eq!(
+            request(&mut server, "textDocument/hover", &at(&uri, 1, 0)),
+            Value::Null
+        );
+
+        let items = request(&mut server, "textDocument/completion", &at(&uri, 1, 0));
+        assert_eq!(items.as_array().unwrap().len(), 1);
+        assert_eq!(items[0]["label"], "DATABASE_URL");
+        assert_eq!(items[0]["insertText"], "DATABASE_URL: ");
+        let items = request(&mut server, "textDocument/completion", &at(&uri, 0, 7));
+        assert_eq!(items, json!([]));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let mut server = Server::new();
+        open(&mut server, "untitled:1", "  A : 1\n");
+        let edits = request(
+            &mut server,
+            "textDocument/formatting",
+            &json!({ "textDocument": { "uri": "untitled:1" }, "options": {} }),
+        );
+        assert_eq!(edits[0]["newText"], "A: 1\n");
+        assert_eq!(edits[0]["range"]["end"], json!({"line": 1, "character": 0}));
+
+        open(&mut server, "untitled:2", "A:  \"open\n");
+        let response = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/formatting",
+            "params": { "textDocument": { "uri": "untitled:2" }, "options": {} },
+        }));
+        assert_eq!(response[0]["error"]["code"], REQUEST_FAILED);
+    }
+
+    #[test]
+    fn test_session_over_stdio() {
+        let mut input = Vec::new();
+        for message in [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "workspace/symbol", "params": {}}),
+            json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+            json!({"jsonrpc": "2.0", "method": "exit"}),
+        ] {
+            write_message(&mut input, &message).unwrap();
+        }
+        let mut output = Vec::new();
+        let code = Server::new().run(&mut &input[..], &mut output).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+
+        let mut output = &output[..];
+        let initialized = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(initialized["result"]["capabilities"]["hoverProvider"], true);
+        let unknown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        let shutdown = read_message(&mut output).unwrap().unwrap();
+        assert_eq!(shutdown, json!({"jsonrpc": "2.0", "id": 3, "result": null}));
+        assert_eq!(read_message(&mut output).unwrap(), None);
+    }
+}
@@ -0,0 +1,79 @@
+//! Language Server Protocol framing: `Content-Length` headers followed by a
+//! JSON body.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Read the next message, or `None` at the end of the input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return if length.is_none() {
+                Ok(None)
+            } else {
+                Err(invalid("unexpected end of input in message header"))
+            };
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim().parse::<usize>();
+                length = Some(value.map_err(|_| invalid("invalid Content-Length"))?);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| invalid(&e.to_string()))
+}
+
+/// Write `message` with its header.
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({"id": 1, "text": "é"})).unwrap();
+        write_message(&mut framed, &json!(null)).unwrap();
+
+        let mut reader = &framed[..];
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some(json!({"id": 1, "text": "é"}))
+        );
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!(null)));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let mut reader = &b"Content-Length: 2\r\n"[..];
+        assert!(read_message(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Files related to a document: its layers, schema and lint config.
+//!
+//! `.lenv` files are layered by name: `.lenv` is the base layer and
+//! `.lenv.local`, `.lenv.production` and so on override it. The schema of a
+//! layer is `LAYER.schema`, falling back to the schema of the base layer.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lino_env::lint::LintConfig;
+
+/// Files next to a layer that are not layers themselves.
+const NOT_LAYERS: [&str; 4] = ["schema", "example", "keys", "lenvlint"];
+
+fn hex(byte: u8) -> Option<u8> {
+    char::from(byte)
+        .to_digit(16)
+        .and_then(|digit| u8::try_from(digit).ok())
+}
+
+/// The path of a `file://` URI.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let decoded = (encoded[index] == b'%')
+            .then(|| Some(hex(*encoded.get(index + 1)?)? * 16 + hex(*encoded.get(index + 2)?)?))
+            .flatten();
+        if let Some(byte) = decoded {
+            bytes.push(byte);
+            index += 3;
+        } else {
+            bytes.push(encoded[index]);
+            index += 1;
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    let path = if cfg!(windows) {
+        path.trim_start_matches('/').to_string()
+    } else {
+        path
+    };
+    Some(PathBuf::from(path))
+}
+
+/// The `file://` URI of `path`.
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    let path = path.to_string_lossy().replace('\\', "/");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~:".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            let _ = write!(uri, "%{byte:02X}");
+        }
+    }
+    uri
+}
+
+/// The base layer name of `name`, e.g. `.lenv` for `.lenv.local`.
+fn base_name(name: &str) -> Option<&str> {
+    let end = name.find(".lenv")? + ".lenv".len();
+    let base = &name[..end];
+    (name.len() == end || name[end..].starts_with('.')).then_some(base)
+}
+
+/// The base layer of `path`, or `path` itself if it is not layered.
+pub fn base_layer(path: &Path) -> PathBuf {
+    let name = path.file_name().and_then(|name| name.to_str());
+    name.and_then(base_name)
+        .map_or_else(|| path.to_path_buf(), |base| path.with_file_name(base))
+}
+
+/// All layers `path` belongs to, base layer first.
+pub fn layers(path: &Path) -> Vec<PathBuf> {
+    let base = base_layer(path);
+    let (Some(dir), Some(base_name)) = (base.parent(), base.file_name()) else {
+        return vec![path.to_path_buf()];
+    };
+    let base_name = base_name.to_string_lossy().into_owned();
+    let prefix = format!("{base_name}.");
+    let mut names: Vec<String> = fs::read_dir(if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    })
+    .into_iter()
+    .flatten()
+    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+    .filter(|name| {
+        name.strip_prefix(&prefix).is_some_and(|suffix| {
+            !suffix
+                .rsplit('.')
+                .next()
+                .is_some_and(|extension| NOT_LAYERS.contains(&extension))
+        })
+    })
+    .collect();
+    names.sort();
+    names.insert(0, base_name);
+    let mut layers: Vec<PathBuf> = names.iter().map(|name| dir.join(name)).collect();
+    if !layers.iter().any(|layer| layer == path) {
+        layers.push(path.to_path_buf());
+    }
+    layers
+}
+
+/// The schema file of `path`, if there is one.
+pub fn schema_path(path: &Path) -> Option<PathBuf> {
+    [path.to_path_buf(), base_layer(path)]
+        .into_iter()
+        .map(|layer| {
+            let mut schema = layer.into_os_string();
+            schema.push(".schema");
+            PathBuf::from(schema)
+        })
+        .find(|schema| schema.is_file())
+}
+
+/// The lint config in the directory of `path` or the nearest parent.
+pub fn lint_config(path: &Path) -> LintConfig {
+    path.ancestors()
+        .skip(1)
+        .map(|dir| dir.join(".lenvlint"))
+        .find(|config| config.is_file())
+        .and_then(|config| LintConfig::read(config).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempDir;
+
+    #[test]
+    fn test_uris() {
+        let path = Path::new("/tmp/my project/.lenv");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/my%20project/.lenv");
+        if cfg!(unix) {
+            assert_eq!(uri_to_path(&uri).as_deref(), Some(path));
+        }
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+
+    #[test]
+    fn test_layers() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        for name in [
+            ".lenv",
+            ".lenv.local",
+            ".lenv.example",
+            ".lenv.schema",
+            "other",
+        ] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let local = dir.join(".lenv.local");
+        assert_eq!(layers(&local), vec![dir.join(".lenv"), local.clone()]);
+        assert_eq!(base_layer(&local), dir.join(".lenv"));
+        assert_eq!(schema_path(&local), Some(dir.join(".lenv.schema")));
+        assert_eq!(layers(&dir.join("other")), vec![dir.join("other")]);
+    }
+}
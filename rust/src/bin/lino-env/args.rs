@@ -0,0 +1,94 @@
+//! Minimal command-line argument parsing shared by all subcommands.
+
+use std::collections::{HashMap, HashSet};
+
+/// Parsed arguments of a single subcommand.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    flags: HashSet<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    /// Parse `args`, accepting only the given `--flag`s and `--option value`s.
+    ///
+    /// Options may also be written as `--option=value`.
+    pub fn parse(args: &[String], flags: &[&str], options: &[&str]) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg.clone());
+                continue;
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                if !options.contains(&name) {
+                    return Err(format!("unknown option `--{name}`"));
+                }
+                parsed.options.insert(name.to_string(), value.to_string());
+            } else if options.contains(&name) {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("option `--{name}` requires a value"))?;
+                parsed.options.insert(name.to_string(), value.clone());
+            } else if flags.contains(&name) {
+                parsed.flags.insert(name.to_string());
+            } else {
+                return Err(format!("unknown option `--{name}`"));
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Whether `--name` was given.
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// The value of `--name`, if given.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    /// Positional arguments, in order.
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// The positional argument at `index`, or `default` if absent.
+    pub fn positional_or<'a>(&'a self, index: usize, default: &'a str) -> &'a str {
+        self.positional.get(index).map_or(default, String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = Args::parse(
+            &strings(&["a.lenv", "--json", "--schema", "s", "--out=x", "b"]),
+            &["json"],
+            &["schema", "out"],
+        )
+        .unwrap();
+        assert_eq!(args.positional, vec!["a.lenv", "b"]);
+        assert!(args.flag("json"));
+        assert_eq!(args.option("schema"), Some("s"));
+        assert_eq!(args.option("out"), Some("x"));
+        assert_eq!(args.positional_or(2, ".lenv"), ".lenv");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Args::parse(&strings(&["--nope"]), &[], &[]).is_err());
+        assert!(Args::parse(&strings(&["--schema"]), &[], &["schema"]).is_err());
+    }
+}
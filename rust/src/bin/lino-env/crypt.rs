@@ -0,0 +1,50 @@
+//! `lino-env encrypt` / `lino-env decrypt` - encrypt values in place.
+
+use std::process::ExitCode;
+
+use lino_env::encryption::PUBLIC_KEY_NAME;
+use lino_env::{read_lino_env, LinoEnv};
+
+use crate::args::Args;
+
+pub const ENCRYPT_USAGE: &str = "lino-env encrypt [FILE] [KEY...]
+
+Encrypt the given KEYs (default: all keys) of FILE (default `.lenv`) in
+place. A keypair is created on first use: the public key is stored in FILE
+and the private key in `FILE.keys`, which must never be committed.";
+
+pub const DECRYPT_USAGE: &str = "lino-env decrypt [FILE] [KEY...]
+
+Decrypt the given KEYs (default: all keys) of FILE (default `.lenv`) in
+place, using LENV_PRIVATE_KEY from the environment or `FILE.keys`.";
+
+fn target_keys(env: &LinoEnv, keys: &[String]) -> Vec<String> {
+    if !keys.is_empty() {
+        return keys.to_vec();
+    }
+    let mut keys: Vec<String> = env
+        .keys()
+        .into_iter()
+        .filter(|key| key != PUBLIC_KEY_NAME)
+        .collect();
+    keys.sort();
+    keys
+}
+
+pub fn run(args: &[String], encrypt: bool) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &[])?;
+    let file = args.positional_or(0, ".lenv");
+    let mut env = read_lino_env(file).map_err(|e| format!("{file}: {e}"))?;
+
+    for key in target_keys(&env, args.positional().get(1..).unwrap_or_default()) {
+        let result = if encrypt {
+            env.encrypt_key(&key)
+        } else {
+            env.decrypt_key(&key)
+        };
+        result.map_err(|e| format!("{file}: {key}: {e}"))?;
+    }
+
+    env.write().map_err(|e| format!("{file}: {e}"))?;
+    Ok(ExitCode::SUCCESS)
+}
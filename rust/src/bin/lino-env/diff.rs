@@ -0,0 +1,93 @@
+//! `lino-env diff` - compare two `.lenv` files by key.
+
+use std::process::ExitCode;
+
+use lino_env::diff::{Change, EnvDiff};
+use lino_env::patch::EnvPatch;
+use lino_env::read_lino_env;
+
+use crate::args::Args;
+use crate::json;
+
+pub const USAGE: &str = "lino-env diff OLD NEW [--format text|json|patch] [--mask]
+
+Compare the effective values of OLD and NEW, ignoring order, comments and
+shadowed duplicate keys. `--mask` hides values of sensitive keys
+(`*_TOKEN`, `*_SECRET`, `*_PASSWORD`). `--format patch` prints the changes
+as a patch for `lino-env patch`, with encrypted values as stored, and
+cannot be combined with `--mask`.
+Exits with 1 if the files differ.";
+
+fn to_json(diff: &EnvDiff) -> String {
+    let entry = |change: &Change| match change {
+        Change::Added { key, value } | Change::Removed { key, value } => format!(
+            r#"{{"key":{},"value":{}}}"#,
+            json::string(key),
+            json::string(value)
+        ),
+        Change::Changed { key, old, new } => format!(
+            r#"{{"key":{},"old":{},"new":{}}}"#,
+            json::string(key),
+            json::string(old),
+            json::string(new)
+        ),
+    };
+    let list = |changes: Vec<&Change>| changes.into_iter().map(entry).collect::<Vec<_>>().join(",");
+    format!(
+        r#"{{"added":[{}],"removed":[{}],"changed":[{}]}}"#,
+        list(diff.added().collect()),
+        list(diff.removed().collect()),
+        list(diff.changed().collect())
+    )
+}
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["mask"], &["format"])?;
+    let [old_path, new_path] = args.positional() else {
+        return Err(format!("expected two files\n\n{USAGE}"));
+    };
+    let format = args.option("format").unwrap_or("text");
+    if !matches!(format, "text" | "json" | "patch") {
+        return Err(format!("unknown format `{format}`"));
+    }
+    if format == "patch" && args.flag("mask") {
+        // Applying a masked patch would write the masks over the secrets.
+        return Err("`--mask` and `--format patch` cannot be combined".to_string());
+    }
+
+    let old = read_lino_env(old_path).map_err(|e| format!("{old_path}: {e}"))?;
+    let new = read_lino_env(new_path).map_err(|e| format!("{new_path}: {e}"))?;
+    let mut diff = old.diff(&new);
+    if args.flag("mask") {
+        diff = diff.masked(|key| old.is_sensitive(key));
+    }
+
+    match format {
+        "json" => println!("{}", to_json(&diff)),
+        "patch" => print!("{}", EnvPatch::between(&old, &new)),
+        _ => print!("{diff}"),
+    }
+
+    Ok(if diff.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_with_patch_format() {
+        let args: Vec<String> = ["old.lenv", "new.lenv", "--format", "patch", "--mask"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            run(&args).unwrap_err(),
+            "`--mask` and `--format patch` cannot be combined"
+        );
+    }
+}
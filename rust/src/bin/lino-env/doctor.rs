@@ -0,0 +1,48 @@
+//! `lino-env doctor` - check `.lenv` files for exposure risks.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use lino_env::LinoEnv;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env doctor [FILE...]
+
+Check each FILE (default `.lenv`) and its `FILE.keys` private key file, if
+present, for permissions readable by other users, a different owner, and
+not being ignored by git. Exits with 1 if any warning is found.";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &[])?;
+    let mut files: Vec<String> = args.positional().to_vec();
+    if files.is_empty() {
+        files.push(".lenv".to_string());
+    }
+
+    let mut warnings = 0;
+    for file in files {
+        let keys = format!("{file}.keys");
+        let mut paths = vec![file];
+        if Path::new(&keys).exists() {
+            paths.push(keys);
+        }
+
+        for path in paths {
+            let found = LinoEnv::new(&path)
+                .audit_permissions()
+                .map_err(|e| format!("{path}: {e}"))?;
+            for warning in &found {
+                eprintln!("{path}: {warning}");
+            }
+            warnings += found.len();
+        }
+    }
+
+    if warnings == 0 {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        eprintln!("{warnings} warning(s) found");
+        Ok(ExitCode::FAILURE)
+    }
+}
@@ -0,0 +1,92 @@
+//! `lino-env example` - generate or check a `.lenv.example` template.
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use lino_env::example::{check, from_env};
+use lino_env::read_lino_env;
+use lino_env::schema::Schema;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env example [FILE] [--output EXAMPLE] [--schema SCHEMA] [--check]
+
+Write EXAMPLE (default `FILE.example`, `-` for stdout) from FILE (default
+`.lenv`), keeping keys and comments and replacing values with schema
+defaults or placeholders. SCHEMA defaults to `FILE.schema` when it exists.
+
+With --check, nothing is written; keys present in only one of FILE and
+EXAMPLE are listed and the exit code is 1 if there are any.";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["check"], &["output", "schema"])?;
+    let file = args.positional_or(0, ".lenv");
+    let output = args
+        .option("output")
+        .map_or_else(|| format!("{file}.example"), ToString::to_string);
+    let env = read_lino_env(file).map_err(|e| format!("{file}: {e}"))?;
+
+    if args.flag("check") {
+        let example = read_lino_env(&output).map_err(|e| format!("{output}: {e}"))?;
+        let drift = check(&env, &example);
+        for key in &drift.missing_from_example {
+            eprintln!("{output}: missing key {key} (present in {file})");
+        }
+        for key in &drift.missing_from_env {
+            eprintln!("{file}: missing key {key} (present in {output})");
+        }
+        return Ok(if drift.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        });
+    }
+
+    let schema_path = args
+        .option("schema")
+        .map_or_else(|| format!("{file}.schema"), ToString::to_string);
+    let schema = if args.option("schema").is_some() || Path::new(&schema_path).exists() {
+        Some(Schema::read(&schema_path).map_err(|e| format!("{schema_path}: {e}"))?)
+    } else {
+        None
+    };
+
+    let content = from_env(&env, schema.as_ref());
+    if output == "-" {
+        print!("{content}");
+    } else {
+        fs::write(&output, content).map_err(|e| format!("{output}: {e}"))?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempLenv;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_writes_and_checks_example() {
+        let (file, output) = (TempLenv::new(), TempLenv::new());
+        let (file, output) = (file.path(), output.path());
+        fs::write(file, "# Token\nTOKEN: secret\n").unwrap();
+
+        let code = run(&args(&[file, "--output", output])).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(
+            fs::read_to_string(output).unwrap(),
+            "# Token\nTOKEN: <value>\n"
+        );
+        let code = run(&args(&[file, "--output", output, "--check"])).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+
+        fs::write(file, "TOKEN: secret\nPORT: 80\n").unwrap();
+        let code = run(&args(&[file, "--output", output, "--check"])).unwrap();
+        assert_eq!(code, ExitCode::FAILURE);
+    }
+}
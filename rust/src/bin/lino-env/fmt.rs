@@ -0,0 +1,78 @@
+//! `lino-env fmt` - format `.lenv` files in place.
+
+use std::fs;
+use std::process::ExitCode;
+
+use lino_env::format::{format_with, FormatOptions, KeyOrder};
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env fmt [FILE...] [--check] [--sort | --group SEPARATOR]
+
+Rewrite each FILE (default `.lenv`) as `KEY: value` lines without stray
+whitespace around keys, with canonical quoting of multi-line values and
+only the last value of duplicated keys. Values and comments are kept; a
+value that would read back differently is an error. `--sort` sorts keys,
+`--group _` keeps keys with the same prefix together. `--check` only lists
+files that are not formatted and exits with 1 if there are any.";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["check", "sort"], &["group"])?;
+    let order = match (args.flag("sort"), args.option("group")) {
+        (true, Some(_)) => return Err("`--sort` and `--group` cannot be combined".to_string()),
+        (true, None) => KeyOrder::Sorted,
+        (false, Some(separator)) => KeyOrder::GroupByPrefix(separator.to_string()),
+        (false, None) => KeyOrder::Preserve,
+    };
+    let options = FormatOptions { order };
+    let mut files: Vec<String> = args.positional().to_vec();
+    if files.is_empty() {
+        files.push(".lenv".to_string());
+    }
+
+    let mut unformatted = 0;
+    for file in &files {
+        let source = fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+        let formatted = format_with(&source, &options).map_err(|e| format!("{file}: {e}"))?;
+        if formatted == source {
+            continue;
+        }
+        if args.flag("check") {
+            println!("{file}");
+            unformatted += 1;
+        } else {
+            fs::write(file, formatted).map_err(|e| format!("{file}: {e}"))?;
+        }
+    }
+
+    Ok(if unformatted == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempLenv;
+
+    #[test]
+    fn test_formats_and_checks() {
+        let file = TempLenv::with_contents("  A : 1\nB:  two \n").unwrap();
+        let check = [file.path().to_string(), "--check".to_string()];
+        assert_eq!(run(&check).unwrap(), ExitCode::FAILURE);
+        assert_eq!(run(&check[..1]).unwrap(), ExitCode::SUCCESS);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "A: 1\nB:  two \n");
+        assert_eq!(run(&check).unwrap(), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn test_keeps_files_that_would_change_values() {
+        let source = "A:  \"open\n";
+        let file = TempLenv::with_contents(source).unwrap();
+        let error = run(&[file.path().to_string()]).unwrap_err();
+        assert!(error.ends_with("`A` cannot be written so that it reads back unchanged"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), source);
+    }
+}
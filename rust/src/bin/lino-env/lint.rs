@@ -0,0 +1,77 @@
+//! `lino-env lint` - check `.lenv` files for common mistakes.
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use lino_env::lint::{LintConfig, Rule, Severity};
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env lint [FILE...] [--config CONFIG] [--list-rules]
+
+Check each FILE (default `.lenv`) for duplicate keys, lines without `: `,
+`KEY=value` lines, trailing whitespace, unterminated quotes, empty values
+and keys that are not environment variable names. CONFIG (default
+`.lenvlint` if it exists) sets rule severities with `rule-id: off`,
+`warning` or `error` lines. Exits with 1 if any error is found.";
+
+fn column(source: &str, offset: usize) -> usize {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    source[line_start..offset].chars().count() + 1
+}
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["list-rules"], &["config"])?;
+    if args.flag("list-rules") {
+        for rule in Rule::ALL {
+            println!(
+                "{:<20} {:<8} {}",
+                rule.id(),
+                rule.default_severity(),
+                rule.description()
+            );
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let config = match args.option("config") {
+        Some(path) => LintConfig::read(path).map_err(|e| format!("{path}: {e}"))?,
+        None if Path::new(".lenvlint").exists() => {
+            LintConfig::read(".lenvlint").map_err(|e| format!(".lenvlint: {e}"))?
+        }
+        None => LintConfig::new(),
+    };
+    let mut files: Vec<String> = args.positional().to_vec();
+    if files.is_empty() {
+        files.push(".lenv".to_string());
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for file in &files {
+        let source = fs::read_to_string(file).map_err(|e| format!("{file}: {e}"))?;
+        for diagnostic in config.lint(&source) {
+            println!(
+                "{file}:{}:{}: {}[{}]: {}",
+                diagnostic.span.start_line,
+                column(&source, diagnostic.span.start),
+                diagnostic.severity,
+                diagnostic.rule.id(),
+                diagnostic.message
+            );
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+    }
+
+    if errors + warnings > 0 {
+        eprintln!("{errors} error(s), {warnings} warning(s)");
+    }
+    Ok(if errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
@@ -0,0 +1,100 @@
+//! `lino-env` - command-line tool for working with `.lenv` files.
+//!
+//! Exit codes: `0` on success, `1` when a check finds problems, `2` on
+//! usage or I/O errors.
+
+mod args;
+#[cfg(feature = "encryption")]
+mod crypt;
+mod diff;
+mod doctor;
+mod example;
+mod fmt;
+mod json;
+mod lint;
+mod merge_driver;
+mod patch;
+mod scan;
+mod validate;
+
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: lino-env <COMMAND> [ARGS]
+
+Commands:
+  decrypt       Decrypt values in place (requires the `encryption` feature)
+  diff          Compare two .lenv files by key
+  doctor        Check .lenv files for exposure risks
+  encrypt       Encrypt values in place (requires the `encryption` feature)
+  example       Generate or check a .lenv.example template
+  fmt           Format .lenv files
+  lint          Check .lenv files for common mistakes
+  merge-driver  Merge .lenv files key by key (for use as a git merge driver)
+  patch         Apply a patch to .lenv files
+  scan          Look for real credentials in .lenv files
+  validate      Check a .lenv file against its schema
+
+Run `lino-env help <COMMAND>` for details on a command.";
+
+fn command_usage(command: &str) -> Option<&'static str> {
+    match command {
+        #[cfg(feature = "encryption")]
+        "decrypt" => Some(crypt::DECRYPT_USAGE),
+        "diff" => Some(diff::USAGE),
+        "doctor" => Some(doctor::USAGE),
+        #[cfg(feature = "encryption")]
+        "encrypt" => Some(crypt::ENCRYPT_USAGE),
+        "example" => Some(example::USAGE),
+        "fmt" => Some(fmt::USAGE),
+        "lint" => Some(lint::USAGE),
+        "merge-driver" => Some(merge_driver::USAGE),
+        "patch" => Some(patch::USAGE),
+        "scan" => Some(scan::USAGE),
+        "validate" => Some(validate::USAGE),
+        _ => None,
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    let Some((command, rest)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return Ok(ExitCode::from(2));
+    };
+
+    match command.as_str() {
+        #[cfg(feature = "encryption")]
+        "decrypt" => crypt::run(rest, false),
+        "diff" => diff::run(rest),
+        "doctor" => doctor::run(rest),
+        #[cfg(feature = "encryption")]
+        "encrypt" => crypt::run(rest, true),
+        "example" => example::run(rest),
+        "fmt" => fmt::run(rest),
+        "lint" => lint::run(rest),
+        "merge-driver" => merge_driver::run(rest),
+        "patch" => patch::run(rest),
+        "scan" => scan::run(rest),
+        "validate" => validate::run(rest),
+        "help" | "-h" | "--help" => {
+            let usage = rest
+                .first()
+                .map_or(Some(USAGE), |command| command_usage(command))
+                .ok_or_else(|| format!("unknown command `{}`", rest[0]))?;
+            println!("{usage}");
+            Ok(ExitCode::SUCCESS)
+        }
+        "version" | "-V" | "--version" => {
+            println!("lino-env {}", lino_env::VERSION);
+            Ok(ExitCode::SUCCESS)
+        }
+        other => Err(format!("unknown command `{other}`\n\n{USAGE}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    run(&args).unwrap_or_else(|message| {
+        eprintln!("lino-env: {message}");
+        ExitCode::from(2)
+    })
+}
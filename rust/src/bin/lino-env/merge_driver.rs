@@ -0,0 +1,88 @@
+//! `lino-env merge-driver` - git merge driver merging `.lenv` files by key.
+
+use std::fs;
+use std::process::ExitCode;
+
+use lino_env::merge3;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env merge-driver BASE OURS THEIRS
+
+Merge OURS and THEIRS key by key and write the result to OURS. Conflicting
+keys and comments are wrapped in conflict markers and the exit code is 1. To use it for
+all .lenv files in a repository:
+
+  git config merge.lenv.driver \"lino-env merge-driver %O %A %B\"
+  echo '*.lenv merge=lenv' >> .gitattributes";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &[])?;
+    let [base, ours, theirs] = args.positional() else {
+        return Err(format!("expected three files\n\n{USAGE}"));
+    };
+    let read = |path: &String| fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+
+    let merged = merge3(&read(base)?, &read(ours)?, &read(theirs)?);
+    fs::write(ours, &merged.text).map_err(|e| format!("{ours}: {e}"))?;
+
+    for conflict in &merged.conflicts {
+        eprintln!("{ours}: conflict: {conflict}");
+    }
+    if merged.layout_conflict {
+        eprintln!("{ours}: conflict: comments changed differently on both sides");
+    }
+    Ok(if merged.is_clean() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempLenv;
+
+    /// Write `base`, `ours` and `theirs` to temporary files and run the driver.
+    fn merge(base: &str, ours: &str, theirs: &str) -> (ExitCode, String) {
+        let files = [base, ours, theirs].map(|text| TempLenv::with_contents(text).unwrap());
+        let paths: Vec<String> = files.iter().map(|file| file.path().to_string()).collect();
+
+        let code = run(&paths).unwrap();
+        (code, fs::read_to_string(&paths[1]).unwrap())
+    }
+
+    #[test]
+    fn test_clean_merge() {
+        let (code, merged) = merge("A: 1\nB: 2\n", "A: 10\nB: 2\n", "A: 1\nB: 20\n");
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(merged, "A: 10\nB: 20\n");
+    }
+
+    #[test]
+    fn test_conflicting_key() {
+        let (code, merged) = merge("A: 1\n", "A: 2\n", "A: 3\n");
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(
+            merged,
+            "<<<<<<< ours\nA: 2\n=======\nA: 3\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_comments() {
+        let (code, merged) = merge("A: 1\n", "# ours\nA: 1\n", "# theirs\nA: 1\n");
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(
+            merged,
+            "# ours\nA: 1\n<<<<<<< ours\n=======\n# theirs\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn test_expects_three_files() {
+        let args = vec!["base.lenv".to_string()];
+        assert!(run(&args).unwrap_err().starts_with("expected three files"));
+    }
+}
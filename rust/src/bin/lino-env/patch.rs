@@ -0,0 +1,78 @@
+//! `lino-env patch` - apply a patch to several `.lenv` files.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use lino_env::patch::EnvPatch;
+use lino_env::read_lino_env;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env patch PATCH FILE... [--dry-run] [--create]
+
+Apply the operations in PATCH (`set KEY VALUE`, `delete KEY`,
+`rename FROM TO`, one per line in links notation) to each FILE and print
+what changed. Files that are already up to date are left untouched, so the
+same patch can be applied repeatedly. `--dry-run` prints the changes
+without writing. A FILE that does not exist is an error unless `--create`
+is given. Generate a patch with `lino-env diff OLD NEW --format patch`.";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &["dry-run", "create"], &[])?;
+    let Some((patch_path, files)) = args.positional().split_first() else {
+        return Err(format!("expected a patch file\n\n{USAGE}"));
+    };
+    if files.is_empty() {
+        return Err(format!("expected at least one file\n\n{USAGE}"));
+    }
+    let patch = EnvPatch::read(patch_path).map_err(|e| format!("{patch_path}: {e}"))?;
+
+    if !args.flag("create") {
+        if let Some(file) = files.iter().find(|file| !Path::new(file).exists()) {
+            return Err(format!("{file}: no such file (use --create to create it)"));
+        }
+    }
+
+    for file in files {
+        let mut env = read_lino_env(file).map_err(|e| format!("{file}: {e}"))?;
+        let diff = env
+            .apply_patch(&patch)
+            .map_err(|e| format!("{file}: {e}"))?;
+        if diff.is_empty() {
+            continue;
+        }
+        println!("{file}:");
+        print!("{diff}");
+        if !args.flag("dry-run") {
+            env.write().map_err(|e| format!("{file}: {e}"))?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempLenv;
+    use std::fs;
+
+    #[test]
+    fn test_missing_file_needs_create() {
+        let patch = TempLenv::with_contents("set A 1\n").unwrap();
+        let file = TempLenv::new();
+        let args = |extra: &[&str]| -> Vec<String> {
+            [patch.path(), file.path()]
+                .iter()
+                .chain(extra)
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        let error = run(&args(&[])).unwrap_err();
+        assert!(error.ends_with("no such file (use --create to create it)"));
+        assert!(!Path::new(file.path()).exists());
+
+        assert_eq!(run(&args(&["--create"])).unwrap(), ExitCode::SUCCESS);
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "A: 1\n");
+    }
+}
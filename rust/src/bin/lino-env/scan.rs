@@ -0,0 +1,100 @@
+//! `lino-env scan` - look for real credentials in `.lenv` files.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use lino_env::scan::{Detector, Finding, Scanner};
+
+use crate::args::Args;
+use crate::json;
+
+pub const USAGE: &str = "lino-env scan [FILE...] [--format text|sarif]
+
+Scan each FILE (default: `.lenv` and `.lenv.example`, when present) for
+GitHub tokens, Telegram bot tokens, AWS keys, JWTs and high-entropy strings.
+`--format sarif` prints a SARIF 2.1.0 report for CI code scanning. Exits
+with 1 if anything is found.";
+
+fn sarif(results: &[(String, Finding)]) -> String {
+    let rules: Vec<String> = Detector::ALL
+        .iter()
+        .map(|detector| {
+            format!(
+                r#"{{"id":{},"shortDescription":{{"text":{}}}}}"#,
+                json::string(detector.id()),
+                json::string(detector.description())
+            )
+        })
+        .collect();
+    let results: Vec<String> = results
+        .iter()
+        .map(|(file, finding)| {
+            let message = format!(
+                "{} in value of {}",
+                finding.detector.description(),
+                finding.key
+            );
+            format!(
+                r#"{{"ruleId":{},"level":"error","message":{{"text":{}}},"locations":[{{"physicalLocation":{{"artifactLocation":{{"uri":{}}},"region":{{"startLine":{}}}}}}}]}}"#,
+                json::string(finding.detector.id()),
+                json::string(&message),
+                json::string(file),
+                finding.line
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{{"tool":{{"driver":{{"name":"lino-env","version":{},"rules":[{}]}}}},"results":[{}]}}]}}"#,
+        json::string(lino_env::VERSION),
+        rules.join(","),
+        results.join(",")
+    )
+}
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &["format"])?;
+    let sarif_output = match args.option("format").unwrap_or("text") {
+        "text" => false,
+        "sarif" => true,
+        other => return Err(format!("unknown format `{other}`")),
+    };
+    let files: Vec<String> = if args.positional().is_empty() {
+        [".lenv", ".lenv.example"]
+            .into_iter()
+            .filter(|file| Path::new(file).exists())
+            .map(ToString::to_string)
+            .collect()
+    } else {
+        args.positional().to_vec()
+    };
+
+    let scanner = Scanner::new();
+    let mut results = Vec::new();
+    for file in files {
+        let findings = scanner
+            .scan_file(&file)
+            .map_err(|e| format!("{file}: {e}"))?;
+        results.extend(findings.into_iter().map(|finding| (file.clone(), finding)));
+    }
+
+    if sarif_output {
+        println!("{}", sarif(&results));
+    } else {
+        for (file, finding) in &results {
+            println!(
+                "{file}:{}: {} [{}] in {}",
+                finding.line,
+                finding.detector.description(),
+                finding.detector.id(),
+                finding.key
+            );
+        }
+    }
+
+    Ok(if results.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
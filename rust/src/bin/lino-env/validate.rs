@@ -0,0 +1,83 @@
+//! `lino-env validate` - check a `.lenv` file against its schema.
+
+use std::process::ExitCode;
+
+use lino_env::read_lino_env;
+use lino_env::schema::Schema;
+
+use crate::args::Args;
+
+pub const USAGE: &str = "lino-env validate [FILE] [--schema SCHEMA]
+
+Check FILE (default `.lenv`) against SCHEMA (default `FILE.schema`) and list
+every violation. Exits with 1 if any violation is found.";
+
+pub fn run(args: &[String]) -> Result<ExitCode, String> {
+    let args = Args::parse(args, &[], &["schema"])?;
+    let file = args.positional_or(0, ".lenv");
+    let schema_path = args
+        .option("schema")
+        .map_or_else(|| format!("{file}.schema"), ToString::to_string);
+
+    let schema = Schema::read(&schema_path).map_err(|e| format!("{schema_path}: {e}"))?;
+    let env = read_lino_env(file).map_err(|e| format!("{file}: {e}"))?;
+
+    let violations = schema.validate(&env);
+    for violation in &violations {
+        eprintln!("{file}: {violation}");
+    }
+
+    if violations.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        eprintln!("{} problem(s) found", violations.len());
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lino_env::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_validates_against_schema_next_to_file() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.join(".lenv");
+        let file = file.to_str().unwrap();
+        fs::write(
+            format!("{file}.schema"),
+            "PORT\n  type: int\n  required: true\n",
+        )
+        .unwrap();
+        let args = [file.to_string()];
+
+        fs::write(file, "PORT: 8080\n").unwrap();
+        assert_eq!(run(&args).unwrap(), ExitCode::SUCCESS);
+
+        fs::write(file, "PORT: eighty\n").unwrap();
+        assert_eq!(run(&args).unwrap(), ExitCode::FAILURE);
+        fs::write(file, "OTHER: 1\n").unwrap();
+        assert_eq!(run(&args).unwrap(), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn test_schema_errors() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.join(".lenv");
+        let schema = dir.join("custom.schema");
+        fs::write(&file, "PORT: 8080\n").unwrap();
+        fs::write(&schema, "PORT: 8080\n").unwrap();
+        let args = [
+            file.to_str().unwrap().to_string(),
+            "--schema".to_string(),
+            schema.to_str().unwrap().to_string(),
+        ];
+
+        let error = run(&args[..1]).unwrap_err();
+        assert!(error.starts_with(&format!("{}.schema: ", file.display())));
+        let error = run(&args).unwrap_err();
+        assert!(error.ends_with("line 1: expected a key name, got `PORT: 8080`"));
+    }
+}
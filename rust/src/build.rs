@@ -0,0 +1,93 @@
+//! Helpers for build scripts.
+//!
+//! [`rustc_env`] passes the entries of a `.lenv` file to the compiler as
+//! environment variables, so the crate can read them with `env!`:
+//!
+//! ```no_run
+//! // build.rs: entries of `defaults.lenv` become available to the crate
+//! // as `env!("API_URL")` and so on.
+//! lino_env::build::rustc_env("defaults.lenv").unwrap();
+//! ```
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{read_lino_env, LinoEnv};
+
+/// Print `cargo:rustc-env` lines for every entry of the file at `path`, and
+/// ask Cargo to rerun the build script when it changes.
+///
+/// # Errors
+///
+/// Returns an error if the file does not exist or cannot be read, or if an
+/// entry cannot be passed to the compiler (see [`write_rustc_env`]).
+pub fn rustc_env<P: AsRef<str>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    if !Path::new(path).exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{path}: file not found"),
+        ));
+    }
+    let env = read_lino_env(path)?;
+    let mut out = io::stdout().lock();
+    writeln!(out, "cargo:rerun-if-changed={path}")?;
+    write_rustc_env(&env, &mut out)
+}
+
+/// Write a `cargo:rustc-env=KEY=VALUE` line for every entry of `env`, in
+/// file order.
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::InvalidData`] for keys containing `=` and for
+/// multi-line keys or values, which the line-based instructions cannot
+/// carry.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::build::write_rustc_env;
+/// use lino_env::LinoEnv;
+///
+/// let mut env = LinoEnv::new("defaults.lenv");
+/// env.set("API_URL", "https://api.example.com");
+/// let mut out = Vec::new();
+/// write_rustc_env(&env, &mut out).unwrap();
+/// assert_eq!(out, b"cargo:rustc-env=API_URL=https://api.example.com\n");
+/// ```
+pub fn write_rustc_env<W: Write>(env: &LinoEnv, out: &mut W) -> io::Result<()> {
+    for key in env.keys() {
+        let value = env.get(&key).unwrap_or_default();
+        let multi_line = |text: &str| text.contains(['\n', '\r']);
+        if key.contains('=') || multi_line(&key) || multi_line(&value) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("`{key}` cannot be passed to rustc: keys cannot contain `=` and values must be on one line"),
+            ));
+        }
+        writeln!(out, "cargo:rustc-env={key}={value}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_multi_line_values() {
+        let mut env = LinoEnv::new("build_test.lenv");
+        env.set("A", "1").set("CERT", "line 1\nline 2");
+        let mut out = Vec::new();
+        let error = write_rustc_env(&env, &mut out).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(out, b"cargo:rustc-env=A=1\n");
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let error = rustc_env("lino_env_test_build_missing.lenv").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,204 @@
+//! `.lenv` values as fallbacks for `clap` arguments.
+//!
+//! clap's `env = "..."` only consults the process environment, captured when
+//! the command is built. [`with_fallback`] makes every argument with an
+//! environment variable also default to the value of that key in one or more
+//! `.lenv` files, without touching the process environment, so several
+//! commands can be parsed against different files at the same time.
+//!
+//! An argument takes the first value found in:
+//!
+//! 1. the command line,
+//! 2. the process environment,
+//! 3. the layers, the last one that sets the key winning,
+//! 4. the argument's own default.
+//!
+//! ```
+//! # #[cfg(feature = "clap")] {
+//! use clap::Parser;
+//! use lino_env::LinoEnv;
+//!
+//! #[derive(Parser)]
+//! struct Cli {
+//!     #[arg(long, env = "LINO_ENV_DOC_PORT", default_value = "80")]
+//!     port: u16,
+//! }
+//!
+//! let mut env = LinoEnv::new(".lenv");
+//! env.set("LINO_ENV_DOC_PORT", "8080");
+//!
+//! let cli: Cli = lino_env::clap::try_parse_from(&[&env], ["app"]).unwrap();
+//! assert_eq!(cli.port, 8080);
+//! let cli: Cli = lino_env::clap::try_parse_from(&[&env], ["app", "--port", "9090"]).unwrap();
+//! assert_eq!(cli.port, 9090);
+//! # }
+//! ```
+
+use std::ffi::OsString;
+
+use ::clap::{Arg, Command, Error, Parser};
+
+use crate::source::EnvSource;
+use crate::LinoEnv;
+
+/// Default every argument of `command` and its subcommands that has an
+/// environment variable to the value of that key in `layers`.
+///
+/// Later layers override earlier ones. A required argument with a value in
+/// `layers` is no longer required. Values of arguments with a value
+/// delimiter are split like environment values, and values of arguments
+/// with `hide_env_values` are left out of the help too.
+#[must_use]
+pub fn with_fallback(command: Command, layers: &[&LinoEnv]) -> Command {
+    with_source(command, layers)
+}
+
+/// Like [`with_fallback`], with values from any [`EnvSource`], such as a
+/// [`Layered`](crate::source::Layered) stack.
+#[must_use]
+pub fn with_source<S: EnvSource + ?Sized>(command: Command, source: &S) -> Command {
+    command
+        .mut_args(|arg| fallback_arg(arg, source))
+        .mut_subcommands(|subcommand| with_source(subcommand, source))
+}
+
+fn fallback_arg<S: EnvSource + ?Sized>(arg: Arg, source: &S) -> Arg {
+    let value = arg
+        .get_env()
+        .and_then(|name| name.to_str())
+        .and_then(|name| source.get(name));
+    let Some(value) = value else {
+        return arg;
+    };
+    let hide = arg.is_hide_env_values_set() && arg.get_action().takes_values();
+    // Unlike environment values, defaults do not satisfy `required`.
+    let arg = arg.required(false);
+    let arg = match arg.get_value_delimiter() {
+        Some(delimiter) => {
+            let values: Vec<String> = value.split(delimiter).map(str::to_string).collect();
+            arg.default_values(values)
+        }
+        None => arg.default_value(value),
+    };
+    if hide {
+        arg.hide_default_value(true)
+    } else {
+        arg
+    }
+}
+
+/// Parse the arguments of the process into `P` with `layers` as fallbacks,
+/// exiting with clap's usage message on error like [`Parser::parse`].
+#[must_use]
+pub fn parse<P: Parser>(layers: &[&LinoEnv]) -> P {
+    try_parse_from(layers, std::env::args_os()).unwrap_or_else(|error| error.exit())
+}
+
+/// Parse `args`, starting with the binary name, into `P` with `layers` as
+/// fallbacks.
+///
+/// # Errors
+///
+/// Returns clap's error if the arguments, or a value from `layers`, are
+/// invalid.
+pub fn try_parse_from<P, I, T>(layers: &[&LinoEnv], args: I) -> Result<P, Error>
+where
+    P: Parser,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut command = with_fallback(P::command(), layers);
+    let mut matches = command.try_get_matches_from_mut(args)?;
+    P::from_arg_matches_mut(&mut matches).map_err(|error| error.format(&mut command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Layered;
+    use crate::testing::EnvGuard;
+    use ::clap::{Parser, Subcommand};
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[arg(long, env = "LINO_ENV_TEST_CLAP_HOST", default_value = "localhost")]
+        host: String,
+        #[arg(long, env = "LINO_ENV_TEST_CLAP_PORT")]
+        port: Option<u16>,
+        #[arg(long, env = "LINO_ENV_TEST_CLAP_VERBOSE")]
+        verbose: bool,
+        #[arg(long, env = "LINO_ENV_TEST_CLAP_TAGS", value_delimiter = ',')]
+        tags: Vec<String>,
+        #[arg(long, env = "LINO_ENV_TEST_CLAP_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+        #[command(subcommand)]
+        command: Option<Commands>,
+    }
+
+    #[derive(Debug, Subcommand)]
+    enum Commands {
+        Serve {
+            #[arg(long, env = "LINO_ENV_TEST_CLAP_WORKERS")]
+            workers: u8,
+        },
+    }
+
+    fn env(entries: &[(&str, &str)]) -> LinoEnv {
+        let mut env = LinoEnv::new("clap_test.lenv");
+        for (key, value) in entries {
+            env.set(key, value);
+        }
+        env
+    }
+
+    #[test]
+    fn test_precedence() {
+        let _guard = EnvGuard::new();
+        let base = env(&[
+            ("LINO_ENV_TEST_CLAP_HOST", "base"),
+            ("LINO_ENV_TEST_CLAP_PORT", "1"),
+        ]);
+        let local = env(&[("LINO_ENV_TEST_CLAP_PORT", "2")]);
+
+        let cli: Cli = try_parse_from(&[], ["app"]).unwrap();
+        assert_eq!((cli.host.as_str(), cli.port), ("localhost", None));
+        let cli: Cli = try_parse_from(&[&base, &local], ["app"]).unwrap();
+        assert_eq!((cli.host.as_str(), cli.port), ("base", Some(2)));
+        let cli: Cli = try_parse_from(&[&base, &local], ["app", "--port", "3"]).unwrap();
+        assert_eq!(cli.port, Some(3));
+
+        let layered = Layered::new().layer(local).layer(base);
+        let command = with_source(<Cli as ::clap::CommandFactory>::command(), &layered);
+        let matches = command.try_get_matches_from(["app"]).unwrap();
+        assert_eq!(matches.get_one::<u16>("port"), Some(&1));
+    }
+
+    #[test]
+    fn test_flags_lists_and_subcommands() {
+        let _guard = EnvGuard::new();
+        let layer = env(&[
+            ("LINO_ENV_TEST_CLAP_VERBOSE", "true"),
+            ("LINO_ENV_TEST_CLAP_TAGS", "a,b"),
+            ("LINO_ENV_TEST_CLAP_WORKERS", "4"),
+        ]);
+        let cli: Cli = try_parse_from(&[&layer], ["app", "serve"]).unwrap();
+        assert!(cli.verbose);
+        assert_eq!(cli.tags, vec!["a", "b"]);
+        assert!(matches!(cli.command, Some(Commands::Serve { workers: 4 })));
+    }
+
+    #[test]
+    fn test_invalid_and_hidden_values() {
+        let _guard = EnvGuard::new();
+        let layer = env(&[
+            ("LINO_ENV_TEST_CLAP_PORT", "eighty"),
+            ("LINO_ENV_TEST_CLAP_TOKEN", "s3cret"),
+        ]);
+        assert!(try_parse_from::<Cli, _, _>(&[&layer], ["app"]).is_err());
+
+        let mut command = with_fallback(<Cli as ::clap::CommandFactory>::command(), &[&layer]);
+        let help = command.render_help().to_string();
+        assert!(help.contains("eighty"));
+        assert!(!help.contains("s3cret"));
+    }
+}
@@ -0,0 +1,504 @@
+//! Comments attached to entries and the order of entries in the file.
+//!
+//! A run of `#` lines directly above an entry is its *leading* comment. A
+//! run that follows an entry and is separated from the next entry by a blank
+//! line is the entry's *trailing* comment. Comments at the top of the file
+//! that are followed by a blank line form the file *header*. A run with
+//! blank lines on both sides, such as a section title, is *detached*: it is
+//! kept in place between the entries around it.
+//!
+//! ```text
+//! # Payments service        <- header
+//!
+//! # owner: payments team    <- leading comment of STRIPE_KEY
+//! STRIPE_KEY: sk_test_123
+//! # rotate monthly          <- trailing comment of STRIPE_KEY
+//!
+//! # Server                  <- detached
+//!
+//! PORT: 8080
+//! ```
+//!
+//! Blank lines between entries are remembered, and comment lines and lines
+//! without a `: ` separator are kept as written, so [`LinoEnv::write`] keeps
+//! the grouping of the original file and drops nothing it could not parse.
+//! The one exception is an indented line directly after a value written as
+//! an indented block, which loses its indentation so that it is not read
+//! back as part of the value.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::parser::format_entry;
+use crate::LinoEnv;
+
+/// A line kept around entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    /// A comment line as written, without trailing whitespace.
+    Comment(String),
+    /// A line the parser could not interpret, written back unchanged.
+    Invalid(String),
+}
+
+impl Line {
+    /// Comment text without the `#` and one following space.
+    fn comment(&self) -> Option<&str> {
+        match self {
+            Self::Comment(line) => {
+                let text = line.trim_start().strip_prefix('#').unwrap_or_default();
+                Some(text.strip_prefix(' ').unwrap_or(text))
+            }
+            Self::Invalid(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Comment(line) | Self::Invalid(line) => write!(f, "{line}"),
+        }
+    }
+}
+
+fn comments(lines: &[Line]) -> Option<String> {
+    let text: Vec<&str> = lines.iter().filter_map(Line::comment).collect();
+    (!text.is_empty()).then(|| text.join("\n"))
+}
+
+fn comment_lines(text: &str) -> Vec<Line> {
+    text.lines()
+        .map(|line| {
+            Line::Comment(if line.is_empty() {
+                "#".to_string()
+            } else {
+                format!("# {line}")
+            })
+        })
+        .collect()
+}
+
+/// Write `lines`; with `dedent` set, the first one without its indentation,
+/// since an indented line right after an entry written as an indented block
+/// would be read back as part of its value.
+fn write_lines<W: Write>(out: &mut W, lines: &[Line], dedent: &mut bool) -> io::Result<()> {
+    for line in lines {
+        let line = line.to_string();
+        if std::mem::take(dedent) {
+            writeln!(out, "{}", line.trim_start())?;
+        } else {
+            writeln!(out, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Comments and spacing around a single entry.
+#[derive(Debug, Clone, Default)]
+pub struct EntryLayout {
+    /// Detached comment blocks between the previous entry and this one,
+    /// each written after a blank line.
+    detached: Vec<Vec<Line>>,
+    leading: Vec<Line>,
+    trailing: Vec<Line>,
+    blank_before: bool,
+}
+
+/// Entry order, comments and header of a file.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    header: Vec<Line>,
+    order: Vec<String>,
+    entries: HashMap<String, EntryLayout>,
+    /// Detached comment blocks after the last entry.
+    footer: Vec<Vec<Line>>,
+}
+
+impl Layout {
+    /// Keys in file order.
+    pub fn keys(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Record `key` at the end of the file unless it is already known.
+    pub fn add(&mut self, key: &str) -> &mut EntryLayout {
+        if !self.entries.contains_key(key) {
+            self.order.push(key.to_string());
+        }
+        self.entries.entry(key.to_string()).or_default()
+    }
+
+    /// Forget `key`; its detached comments move to the next entry.
+    pub fn remove(&mut self, key: &str) {
+        let Some(entry) = self.entries.remove(key) else {
+            return;
+        };
+        let index = self.order.iter().position(|existing| existing == key);
+        let index = index.expect("every entry is in the order");
+        self.order.remove(index);
+        let mut detached = entry.detached;
+        if detached.is_empty() {
+            return;
+        }
+        let next = self
+            .order
+            .get(index)
+            .and_then(|next| self.entries.get_mut(next));
+        let blocks = next.map_or(&mut self.footer, |next| &mut next.detached);
+        detached.append(blocks);
+        *blocks = detached;
+    }
+
+    /// Give `from` the name `to`, keeping its position and comments.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        self.remove(to);
+        if let Some(entry) = self.entries.remove(from) {
+            self.entries.insert(to.to_string(), entry);
+            for key in &mut self.order {
+                if key == from {
+                    *key = to.to_string();
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Set whether a blank line separates `key` from the previous entry.
+    /// Without one, detached comments above `key` become leading comments.
+    pub fn set_blank_before(&mut self, key: &str, blank: bool) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.blank_before = blank;
+            if !blank {
+                let mut lines: Vec<Line> = entry.detached.drain(..).flatten().collect();
+                lines.append(&mut entry.leading);
+                entry.leading = lines;
+            }
+        }
+    }
+
+    /// Write the header, then `keys` with their comments and values.
+    pub fn write_to<W: Write>(
+        &self,
+        out: &mut W,
+        keys: &[&String],
+        data: &HashMap<String, String>,
+    ) -> io::Result<()> {
+        let mut dedent = false;
+        write_lines(out, &self.header, &mut dedent)?;
+        let mut written = !self.header.is_empty();
+        let mut first = true;
+        for key in keys {
+            let Some(value) = data.get(*key) else {
+                continue;
+            };
+            let entry = self.entries.get(*key);
+            let blank_before = entry.is_some_and(|e| e.blank_before);
+            let mut blank = if first { written } else { blank_before };
+            for block in entry.map_or(&[][..], |entry| &entry.detached) {
+                if written {
+                    writeln!(out)?;
+                }
+                write_lines(out, block, &mut dedent)?;
+                written = true;
+                blank = blank_before;
+            }
+            if blank {
+                writeln!(out)?;
+            }
+            first = false;
+            written = true;
+            if let Some(entry) = entry {
+                write_lines(out, &entry.leading, &mut dedent)?;
+            }
+            let text = format_entry(key, value);
+            out.write_all(text.as_bytes())?;
+            dedent = text.starts_with(&format!("{key}:\n"));
+            if let Some(entry) = entry {
+                write_lines(out, &entry.trailing, &mut dedent)?;
+            }
+        }
+        for block in &self.footer {
+            if written {
+                writeln!(out)?;
+            }
+            write_lines(out, block, &mut dedent)?;
+            written = true;
+        }
+        Ok(())
+    }
+}
+
+/// Assigns comment lines to entries while a file is being read.
+#[derive(Debug, Default)]
+pub struct CommentCollector {
+    lines: Vec<Line>,
+    detached: Vec<Vec<Line>>,
+    blank: bool,
+    last_key: Option<String>,
+}
+
+impl CommentCollector {
+    pub fn comment(&mut self, indent: &str, text: &str) {
+        let line = format!("{indent}#{text}");
+        self.lines.push(Line::Comment(line.trim_end().to_string()));
+    }
+
+    pub fn invalid(&mut self, text: &str) {
+        self.lines.push(Line::Invalid(text.trim_end().to_string()));
+    }
+
+    pub fn blank_line(&mut self, layout: &mut Layout) {
+        self.detach(layout);
+        self.blank = true;
+    }
+
+    pub fn entry(&mut self, layout: &mut Layout, key: &str) {
+        let is_new = !layout.entries.contains_key(key);
+        let leading = std::mem::take(&mut self.lines);
+        let detached = std::mem::take(&mut self.detached);
+        let entry = layout.add(key);
+        if is_new {
+            entry.detached = detached;
+            entry.leading = leading;
+            entry.blank_before = self.blank;
+        } else {
+            entry.detached.extend(detached);
+            entry.leading.extend(leading);
+        }
+        self.blank = false;
+        self.last_key = Some(key.to_string());
+    }
+
+    pub fn finish(mut self, layout: &mut Layout) {
+        self.detach(layout);
+        layout.footer.append(&mut self.detached);
+    }
+
+    /// Comments not directly above an entry are detached if a blank line
+    /// precedes them, and otherwise belong to the previous entry, or to the
+    /// header before the first one.
+    fn detach(&mut self, layout: &mut Layout) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let lines = std::mem::take(&mut self.lines);
+        if self.blank {
+            self.detached.push(lines);
+            return;
+        }
+        match self
+            .last_key
+            .as_ref()
+            .and_then(|key| layout.entries.get_mut(key))
+        {
+            Some(entry) => entry.trailing.extend(lines),
+            None => layout.header.extend(lines),
+        }
+    }
+}
+
+impl LinoEnv {
+    /// The comment lines directly above `key`, without the `#`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.read_from(&b"# owner: payments team\nSTRIPE_KEY: sk_test\n"[..]).unwrap();
+    /// assert_eq!(env.comment("STRIPE_KEY"), Some("owner: payments team".to_string()));
+    /// ```
+    #[must_use]
+    pub fn comment(&self, key: &str) -> Option<String> {
+        let entry = self.layout.entries.get(self.resolve_key(key).as_ref())?;
+        comments(&entry.leading)
+    }
+
+    /// Set the comment written above `key`; an empty text removes it.
+    ///
+    /// Does nothing if `key` does not exist.
+    pub fn set_comment(&mut self, key: &str, text: &str) -> &mut Self {
+        let key = self.resolve_key(key).into_owned();
+        if let Some(entry) = self.layout.entries.get_mut(&key) {
+            entry.leading = comment_lines(text);
+        }
+        self
+    }
+
+    /// The comment lines written after `key` and before the next blank line.
+    #[must_use]
+    pub fn trailing_comment(&self, key: &str) -> Option<String> {
+        let entry = self.layout.entries.get(self.resolve_key(key).as_ref())?;
+        comments(&entry.trailing)
+    }
+
+    /// Set the comment written after `key`; an empty text removes it.
+    ///
+    /// Does nothing if `key` does not exist.
+    pub fn set_trailing_comment(&mut self, key: &str, text: &str) -> &mut Self {
+        let key = self.resolve_key(key).into_owned();
+        if let Some(entry) = self.layout.entries.get_mut(&key) {
+            entry.trailing = comment_lines(text);
+        }
+        self
+    }
+
+    /// The comment at the top of the file, separated from the first entry by
+    /// a blank line.
+    #[must_use]
+    pub fn header(&self) -> Option<String> {
+        comments(&self.layout.header)
+    }
+
+    /// Set the file header comment; an empty text removes it.
+    pub fn set_header(&mut self, text: &str) -> &mut Self {
+        self.layout.header = comment_lines(text);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# Payments service
+
+# owner: payments team
+STRIPE_KEY: sk_test_123
+# rotate monthly
+
+PORT: 8080
+#
+# debug only
+DEBUG: true
+";
+
+    fn read(source: &str) -> LinoEnv {
+        let mut env = LinoEnv::new("comments_test.lenv");
+        env.read_from(source.as_bytes()).unwrap();
+        env
+    }
+
+    fn written(env: &LinoEnv) -> String {
+        let keys: Vec<&String> = env.layout.keys().iter().collect();
+        let mut out = Vec::new();
+        env.layout.write_to(&mut out, &keys, &env.data).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_read_comments() {
+        let env = read(SOURCE);
+        assert_eq!(env.header(), Some("Payments service".to_string()));
+        assert_eq!(
+            env.comment("STRIPE_KEY"),
+            Some("owner: payments team".to_string())
+        );
+        assert_eq!(
+            env.trailing_comment("STRIPE_KEY"),
+            Some("rotate monthly".to_string())
+        );
+        assert_eq!(env.comment("PORT"), None);
+        assert_eq!(env.comment("DEBUG"), Some("\ndebug only".to_string()));
+        assert_eq!(env.keys(), vec!["STRIPE_KEY", "PORT", "DEBUG"]);
+    }
+
+    #[test]
+    fn test_write_preserves_comments() {
+        assert_eq!(written(&read(SOURCE)), SOURCE);
+    }
+
+    #[test]
+    fn test_set_comments() {
+        let mut env = read("A: 1\nB: 2\n");
+        env.set_header("Generated")
+            .set_comment("B", "first\nsecond")
+            .set_trailing_comment("A", "after A")
+            .set_comment("MISSING", "ignored");
+        assert_eq!(
+            written(&env),
+            "# Generated\n\nA: 1\n# after A\n# first\n# second\nB: 2\n"
+        );
+
+        env.set_comment("B", "").delete("A");
+        assert_eq!(written(&env), "# Generated\n\nB: 2\n");
+    }
+
+    #[test]
+    fn test_detached_comments_round_trip() {
+        for source in [
+            "A: 1\n\n# Section B\n\nB: 2\n",
+            "# header\n\n# section\n\nA: 1\n",
+            "A: 1\n\n# one\n\n# two\n\nB: 2\n\n# end\n",
+            "A: 1\n\n# about B\nB: 2\n",
+        ] {
+            assert_eq!(written(&read(source)), source);
+        }
+
+        let env = read("A: 1\n\n# Section B\n\nB: 2\n");
+        assert_eq!(env.trailing_comment("A"), None);
+        assert_eq!(env.comment("B"), None);
+    }
+
+    #[test]
+    fn test_comments_kept_as_written() {
+        let source = "#compact\n  # indented\nA: 1\n\t#  tabbed\n";
+        let env = read(source);
+        assert_eq!(env.comment("A"), Some("compact\nindented".to_string()));
+        assert_eq!(env.trailing_comment("A"), Some(" tabbed".to_string()));
+        assert_eq!(written(&env), source);
+    }
+
+    #[test]
+    fn test_delete_keeps_detached_comments() {
+        let mut env = read("A: 1\n\n# Section B\n\nB: 2\n\n# end\n");
+        env.delete("B");
+        assert_eq!(written(&env), "A: 1\n\n# Section B\n\n# end\n");
+        env.delete("A");
+        assert_eq!(written(&env), "# Section B\n\n# end\n");
+    }
+
+    #[test]
+    fn test_comment_only_file() {
+        let env = read("# just a note\n");
+        assert_eq!(env.header(), Some("just a note".to_string()));
+        assert_eq!(written(&env), "# just a note\n");
+    }
+
+    #[test]
+    fn test_lines_without_separator_are_kept() {
+        let source = "# about A\nbroken line\nA: 1\nKEY=value\n\nstray\n";
+        let mut env = read(source);
+        assert_eq!(env.keys(), vec!["A"]);
+        assert_eq!(env.comment("A"), Some("about A".to_string()));
+        assert_eq!(written(&env), source);
+
+        env.set("B", "2");
+        assert_eq!(
+            written(&env),
+            "# about A\nbroken line\nA: 1\nKEY=value\nB: 2\n\nstray\n"
+        );
+    }
+
+    #[test]
+    fn test_indented_lines_after_blocks_stay_outside() {
+        let source = "A: \"a\nb\"\n  # note\n\n  stray\nB: \"c\nd\"\n\n\tbroken\n";
+        let env = read(source);
+        let output = written(&env);
+        assert_eq!(
+            output,
+            "A:\n  a\n  b\n# note\n\n  stray\nB:\n  c\n  d\n\nbroken\n"
+        );
+        let again = read(&output);
+        assert_eq!(again.data, env.data);
+        assert_eq!(again.trailing_comment("A"), Some("note".to_string()));
+        assert_eq!(written(&again), output);
+    }
+}
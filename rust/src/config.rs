@@ -0,0 +1,245 @@
+//! Typed configuration structs.
+//!
+//! [`LinoEnvConfig`] maps the keys of a [`LinoEnv`] to the fields of a
+//! struct, so values are parsed once at load time instead of looked up by
+//! string throughout a program. With the `derive` feature it can be derived:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use lino_env::{LinoEnv, LinoEnvConfig};
+//!
+//! #[derive(LinoEnvConfig)]
+//! struct Settings {
+//!     /// Base URL of the API
+//!     #[lenv(key = "API_URL")]
+//!     api_url: String,
+//!     #[lenv(default = "8080")]
+//!     port: u16,
+//!     log_level: Option<String>,
+//! }
+//!
+//! let mut env = LinoEnv::new(".lenv");
+//! env.set("API_URL", "https://api.example.com");
+//! let settings = Settings::from_lino_env(&env).unwrap();
+//! assert_eq!(settings.port, 8080);
+//! assert_eq!(settings.log_level, None);
+//!
+//! assert_eq!(
+//!     Settings::example(),
+//!     "# Base URL of the API\nAPI_URL: <string>\nPORT: 8080\nLOG_LEVEL: <string>\n"
+//! );
+//! # }
+//! ```
+//!
+//! Each field is read from the key named by `#[lenv(key = "...")]`, or the
+//! field name in uppercase, and parsed with [`FromStr`]. A missing key is
+//! replaced by `#[lenv(default = "...")]`, or left as `None` for `Option`
+//! fields; any other missing key is an error. `///` comments on fields
+//! become schema descriptions and comments in [`LinoEnvConfig::to_lino_env`].
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use crate::schema::Schema;
+use crate::{example, read_lino_env, LinoEnv};
+
+/// Error returned when a configuration struct cannot be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// A key without a default is not set.
+    Missing { key: String },
+    /// A value could not be parsed into the field type.
+    Invalid { key: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Missing { key } => write!(f, "`{key}` is required but not set"),
+            Self::Invalid { key, message } => write!(f, "`{key}` has an invalid value: {message}"),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Missing { .. } | Self::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A struct loaded from the keys of a [`LinoEnv`].
+pub trait LinoEnvConfig: Sized {
+    /// Read every field from `env`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConfigError::Missing`] or [`ConfigError::Invalid`] for the
+    /// first field that cannot be read.
+    fn from_lino_env(env: &LinoEnv) -> Result<Self, ConfigError>;
+
+    /// Store every field in a new environment for the file `.lenv`.
+    fn to_lino_env(&self) -> LinoEnv;
+
+    /// The keys read by [`LinoEnvConfig::from_lino_env`] with their types,
+    /// defaults and descriptions.
+    fn schema() -> Schema;
+
+    /// Read the file at `path` and load the struct from it.
+    ///
+    /// Like [`LinoEnv::read`], a missing file is read as empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a field cannot be
+    /// loaded.
+    fn load<P: AsRef<str>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_lino_env(&read_lino_env(path)?)
+    }
+
+    /// Content of a `.lenv.example` template for this struct.
+    #[must_use]
+    fn example() -> String {
+        example::generate("", Some(&Self::schema()))
+    }
+}
+
+/// Parse the value of `key`, falling back to `default`, or return `None` if
+/// neither is set.
+///
+/// # Errors
+///
+/// Returns [`ConfigError::Invalid`] if the value cannot be parsed.
+pub fn parse_optional<T>(
+    env: &LinoEnv,
+    key: &str,
+    default: Option<&str>,
+) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    env.get(key)
+        .or_else(|| default.map(ToString::to_string))
+        .map(|value| {
+            value.parse().map_err(|error: T::Err| ConfigError::Invalid {
+                key: key.to_string(),
+                message: error.to_string(),
+            })
+        })
+        .transpose()
+}
+
+/// Parse the value of `key`, falling back to `default`.
+///
+/// # Errors
+///
+/// Returns [`ConfigError::Missing`] if neither is set and
+/// [`ConfigError::Invalid`] if the value cannot be parsed.
+pub fn parse_value<T>(env: &LinoEnv, key: &str, default: Option<&str>) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    parse_optional(env, key, default)?.ok_or_else(|| ConfigError::Missing {
+        key: key.to_string(),
+    })
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+    use crate::schema::KeyType;
+    use crate::testing::TempLenv;
+    use crate::LinoEnvConfig;
+
+    #[derive(Debug, PartialEq, LinoEnvConfig)]
+    struct Settings {
+        /// Where the API lives
+        #[lenv(key = "API_URL")]
+        url: String,
+        #[lenv(default = "8080")]
+        port: u16,
+        debug: Option<bool>,
+    }
+
+    #[test]
+    fn test_from_lino_env() {
+        let mut env = LinoEnv::new("config_test.lenv");
+        env.set("API_URL", "https://api").set("DEBUG", "true");
+        assert_eq!(
+            Settings::from_lino_env(&env).unwrap(),
+            Settings {
+                url: "https://api".to_string(),
+                port: 8080,
+                debug: Some(true),
+            }
+        );
+
+        env.set("PORT", "eighty");
+        let error = Settings::from_lino_env(&env).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`PORT` has an invalid value: invalid digit found in string"
+        );
+        env.delete("API_URL");
+        let error = Settings::from_lino_env(&env).unwrap_err();
+        assert_eq!(error.to_string(), "`API_URL` is required but not set");
+    }
+
+    #[test]
+    fn test_to_lino_env_round_trip() {
+        let settings = Settings {
+            url: "https://api".to_string(),
+            port: 9000,
+            debug: None,
+        };
+        let env = settings.to_lino_env();
+        assert_eq!(env.keys(), vec!["API_URL", "PORT"]);
+        assert_eq!(
+            env.comment("API_URL"),
+            Some("Where the API lives".to_string())
+        );
+        assert_eq!(Settings::from_lino_env(&env).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = Settings::schema();
+        let url = schema.get("API_URL").unwrap();
+        assert!(url.required);
+        assert_eq!(url.description.as_deref(), Some("Where the API lives"));
+        let port = schema.get("PORT").unwrap();
+        assert!(matches!(port.key_type, KeyType::Int));
+        assert!(!port.required);
+        assert!(matches!(
+            schema.get("DEBUG").unwrap().key_type,
+            KeyType::Bool
+        ));
+        assert!(Schema::parse(&schema.to_string()).is_ok());
+    }
+
+    #[test]
+    fn test_load() {
+        let file = TempLenv::with_contents("API_URL: https://api\nPORT: 1\n").unwrap();
+        let settings = Settings::load(file.path()).unwrap();
+        assert_eq!(settings.port, 1);
+        assert!(matches!(
+            Settings::load(TempLenv::new().path()),
+            Err(ConfigError::Missing { key }) if key == "API_URL"
+        ));
+    }
+}
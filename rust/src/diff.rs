@@ -0,0 +1,233 @@
+//! Semantic comparison of two environments.
+//!
+//! Unlike a textual diff, the comparison ignores ordering, comments and
+//! duplicate keys: only the effective value of each key (last value wins)
+//! is compared.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::secret::REDACTED;
+use crate::LinoEnv;
+
+/// A single difference between two environments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The key only exists in the second environment.
+    Added { key: String, value: String },
+    /// The key only exists in the first environment.
+    Removed { key: String, value: String },
+    /// The key exists in both with different values.
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl Change {
+    /// The key this change refers to.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Added { key, .. } | Self::Removed { key, .. } | Self::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { key, value } => write!(f, "+ {key}: {value}"),
+            Self::Removed { key, value } => write!(f, "- {key}: {value}"),
+            Self::Changed { key, old, new } => write!(f, "~ {key}: {old} -> {new}"),
+        }
+    }
+}
+
+/// The differences between two environments, sorted by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvDiff {
+    /// All changes, sorted by key.
+    pub changes: Vec<Change>,
+}
+
+impl EnvDiff {
+    /// `true` if both environments have the same keys and values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Keys only present in the second environment.
+    pub fn added(&self) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(|change| matches!(change, Change::Added { .. }))
+    }
+
+    /// Keys only present in the first environment.
+    pub fn removed(&self) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(|change| matches!(change, Change::Removed { .. }))
+    }
+
+    /// Keys whose value differs.
+    pub fn changed(&self) -> impl Iterator<Item = &Change> {
+        self.changes
+            .iter()
+            .filter(|change| matches!(change, Change::Changed { .. }))
+    }
+
+    /// Copy of the diff with the values of keys matching `is_sensitive`
+    /// replaced by `***`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut a = LinoEnv::new("a.lenv");
+    /// a.set("API_TOKEN", "old");
+    /// let mut b = LinoEnv::new("b.lenv");
+    /// b.set("API_TOKEN", "new");
+    ///
+    /// let diff = a.diff(&b).masked(|key| a.is_sensitive(key));
+    /// assert_eq!(diff.changes[0].to_string(), "~ API_TOKEN: *** -> ***");
+    /// ```
+    #[must_use]
+    pub fn masked<F: Fn(&str) -> bool>(&self, is_sensitive: F) -> Self {
+        let mask = |key: &str, value: &String| {
+            if is_sensitive(key) {
+                REDACTED.to_string()
+            } else {
+                value.clone()
+            }
+        };
+        let changes = self
+            .changes
+            .iter()
+            .map(|change| match change {
+                Change::Added { key, value } => Change::Added {
+                    key: key.clone(),
+                    value: mask(key, value),
+                },
+                Change::Removed { key, value } => Change::Removed {
+                    key: key.clone(),
+                    value: mask(key, value),
+                },
+                Change::Changed { key, old, new } => Change::Changed {
+                    key: key.clone(),
+                    old: mask(key, old),
+                    new: mask(key, new),
+                },
+            })
+            .collect();
+        Self { changes }
+    }
+}
+
+impl fmt::Display for EnvDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl LinoEnv {
+    /// Compare this environment with `other`.
+    ///
+    /// Values are compared as returned by [`LinoEnv::get`], so encrypted
+    /// values are compared by their plaintext when it is available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::diff::Change;
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut staging = LinoEnv::new("staging.lenv");
+    /// staging.set("HOST", "staging.example.com").set("DEBUG", "true");
+    /// let mut production = LinoEnv::new("production.lenv");
+    /// production.set("HOST", "example.com").set("REPLICAS", "3");
+    ///
+    /// let diff = staging.diff(&production);
+    /// assert_eq!(
+    ///     diff.to_string(),
+    ///     "- DEBUG: true\n~ HOST: staging.example.com -> example.com\n+ REPLICAS: 3\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> EnvDiff {
+        let keys: BTreeSet<String> = self.keys().into_iter().chain(other.keys()).collect();
+        let changes = keys
+            .into_iter()
+            .filter_map(|key| match (self.get(&key), other.get(&key)) {
+                (None, Some(value)) => Some(Change::Added { key, value }),
+                (Some(value), None) => Some(Change::Removed { key, value }),
+                (Some(old), Some(new)) if old != new => Some(Change::Changed { key, old, new }),
+                _ => None,
+            })
+            .collect();
+        EnvDiff { changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> LinoEnv {
+        let mut env = LinoEnv::new("diff_test.lenv");
+        for (key, value) in pairs {
+            env.set(key, value);
+        }
+        env
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = env(&[("SAME", "1"), ("OLD", "x"), ("CHANGED", "a")]);
+        let b = env(&[("SAME", "1"), ("NEW", "y"), ("CHANGED", "b")]);
+        let diff = a.diff(&b);
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                Change::Changed {
+                    key: "CHANGED".to_string(),
+                    old: "a".to_string(),
+                    new: "b".to_string()
+                },
+                Change::Added {
+                    key: "NEW".to_string(),
+                    value: "y".to_string()
+                },
+                Change::Removed {
+                    key: "OLD".to_string(),
+                    value: "x".to_string()
+                },
+            ]
+        );
+        assert_eq!(diff.added().count(), 1);
+        assert_eq!(diff.removed().count(), 1);
+        assert_eq!(diff.changed().count(), 1);
+    }
+
+    #[test]
+    fn test_identical() {
+        let a = env(&[("A", "1")]);
+        assert!(a.diff(&a.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_masked() {
+        let a = env(&[("DB_PASSWORD", "old"), ("HOST", "a")]);
+        let b = env(&[("HOST", "b")]);
+        let diff = a.diff(&b).masked(|key| a.is_sensitive(key));
+        assert_eq!(diff.to_string(), "- DB_PASSWORD: ***\n~ HOST: a -> b\n");
+    }
+}
@@ -0,0 +1,435 @@
+//! Public-key encryption of values, in the style of dotenvx.
+//!
+//! Encrypted values are stored as `encrypted:<base64>` sealed boxes
+//! (X25519 + XSalsa20-Poly1305). The public key lives in the `.lenv` file
+//! itself as [`PUBLIC_KEY_NAME`], so anyone can add secrets; the private key
+//! is read from the [`PRIVATE_KEY_NAME`] environment variable or from the
+//! `.lenv.keys` file next to the `.lenv` file, and is needed to decrypt.
+//!
+//! Requires the `encryption` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use lino_env::LinoEnv;
+//! use std::fs;
+//!
+//! let path = std::env::temp_dir().join("test_lino_env_encryption_doc.lenv");
+//! let path = path.to_str().unwrap();
+//!
+//! let mut env = LinoEnv::new(path);
+//! env.set("API_KEY", "secret");
+//! env.encrypt_key("API_KEY").unwrap();
+//! env.write().unwrap();
+//!
+//! let raw = fs::read_to_string(path).unwrap();
+//! assert!(raw.contains("API_KEY: encrypted:"));
+//!
+//! // Decryption is transparent as long as the private key can be found.
+//! let env = lino_env::read_lino_env(path).unwrap();
+//! assert_eq!(env.get("API_KEY"), Some("secret".to_string()));
+//!
+//! fs::remove_file(path).ok();
+//! fs::remove_file(lino_env::encryption::keys_path(path)).ok();
+//! ```
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::io;
+use std::sync::OnceLock;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crypto_box::aead::OsRng;
+use crypto_box::{PublicKey, SecretKey};
+
+use crate::{read_lino_env, LinoEnv};
+
+/// Key under which the public key is stored in the `.lenv` file header.
+pub const PUBLIC_KEY_NAME: &str = "LENV_PUBLIC_KEY";
+
+/// Environment variable and `.lenv.keys` entry holding the private key.
+pub const PRIVATE_KEY_NAME: &str = "LENV_PRIVATE_KEY";
+
+/// Prefix marking an encrypted value.
+pub const ENCRYPTED_PREFIX: &str = "encrypted:";
+
+/// Error returned by encryption operations.
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// The `.lenv.keys` file could not be read or written.
+    Io(io::Error),
+    /// The key to encrypt or decrypt does not exist.
+    UnknownKey(String),
+    /// No private key was found in the environment or `.lenv.keys`.
+    MissingPrivateKey,
+    /// A new keypair is needed, but a private key already exists in the
+    /// environment or `.lenv.keys` and would be replaced.
+    ExistingPrivateKey,
+    /// A public or private key is not 64 hexadecimal characters.
+    InvalidKey,
+    /// A value could not be decrypted with the private key.
+    Decrypt,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::UnknownKey(key) => write!(f, "unknown key `{key}`"),
+            Self::MissingPrivateKey => write!(
+                f,
+                "no {PRIVATE_KEY_NAME} in the environment or .lenv.keys file"
+            ),
+            Self::ExistingPrivateKey => write!(
+                f,
+                "no {PUBLIC_KEY_NAME} in the file, but a {PRIVATE_KEY_NAME} already exists in the environment or .lenv.keys file"
+            ),
+            Self::InvalidKey => write!(f, "key is not 64 hexadecimal characters"),
+            Self::Decrypt => write!(f, "value could not be decrypted"),
+        }
+    }
+}
+
+impl Error for EncryptionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EncryptionError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Path of the private key file belonging to a `.lenv` file.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(lino_env::encryption::keys_path(".lenv"), ".lenv.keys");
+/// ```
+#[must_use]
+pub fn keys_path(file_path: &str) -> String {
+    format!("{file_path}.keys")
+}
+
+/// Whether a stored value is encrypted.
+#[must_use]
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn from_hex(hex: &str) -> Result<[u8; 32], EncryptionError> {
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(EncryptionError::InvalidKey);
+    }
+    let mut bytes = [0; 32];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)
+            .map_err(|_| EncryptionError::InvalidKey)?;
+    }
+    Ok(bytes)
+}
+
+/// Generate a new keypair, returned as `(public, private)` hex strings.
+#[must_use]
+pub fn generate_keypair() -> (String, String) {
+    let secret = SecretKey::generate(&mut OsRng);
+    (
+        to_hex(secret.public_key().as_bytes()),
+        to_hex(&secret.to_bytes()),
+    )
+}
+
+/// Encrypt `plaintext` for the given hex public key.
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::InvalidKey`] if the public key is malformed.
+pub fn encrypt_value(public_key: &str, plaintext: &str) -> Result<String, EncryptionError> {
+    let public_key = PublicKey::from(from_hex(public_key)?);
+    let sealed = public_key
+        .seal(&mut OsRng, plaintext.as_bytes())
+        .map_err(|_| EncryptionError::InvalidKey)?;
+    Ok(format!("{ENCRYPTED_PREFIX}{}", BASE64.encode(sealed)))
+}
+
+fn decrypt_with(secret: &SecretKey, value: &str) -> Result<String, EncryptionError> {
+    let encoded = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or(EncryptionError::Decrypt)?;
+    let sealed = BASE64
+        .decode(encoded.trim())
+        .map_err(|_| EncryptionError::Decrypt)?;
+    let plaintext = secret
+        .unseal(&sealed)
+        .map_err(|_| EncryptionError::Decrypt)?;
+    String::from_utf8(plaintext).map_err(|_| EncryptionError::Decrypt)
+}
+
+/// Decrypt an `encrypted:...` value with the given hex private key.
+///
+/// # Errors
+///
+/// Returns [`EncryptionError::InvalidKey`] if the private key is malformed
+/// and [`EncryptionError::Decrypt`] if the value cannot be decrypted.
+pub fn decrypt_value(private_key: &str, value: &str) -> Result<String, EncryptionError> {
+    decrypt_with(&SecretKey::from(from_hex(private_key)?), value)
+}
+
+/// Find the private key for a `.lenv` file: the environment variable wins
+/// over the `.lenv.keys` file.
+fn load_private_key(file_path: &str) -> Result<Option<SecretKey>, EncryptionError> {
+    let hex = match env::var(PRIVATE_KEY_NAME) {
+        Ok(hex) => Some(hex),
+        Err(_) => read_lino_env(keys_path(file_path))?.get(PRIVATE_KEY_NAME),
+    };
+    hex.map(|hex| from_hex(&hex).map(SecretKey::from))
+        .transpose()
+}
+
+impl LinoEnv {
+    /// The private key, loaded on first use. A missing key is looked up
+    /// again next time, so a `.lenv.keys` file created later is found.
+    fn private_key(&self) -> Result<&SecretKey, EncryptionError> {
+        if let Some(secret) = self.private_key.get() {
+            return Ok(secret);
+        }
+        let secret =
+            load_private_key(&self.file_path)?.ok_or(EncryptionError::MissingPrivateKey)?;
+        // A concurrent load of the same file yields the same key.
+        Ok(self.private_key.get_or_init(|| secret))
+    }
+
+    /// Decrypt a stored value, or `None` if it cannot be decrypted.
+    pub(crate) fn decrypted(&self, value: &str) -> Option<String> {
+        if !is_encrypted(value) {
+            return None;
+        }
+        decrypt_with(self.private_key().ok()?, value).ok()
+    }
+
+    /// Get the value of a reference, decrypting `encrypted:...` values.
+    ///
+    /// Where [`LinoEnv::get`] falls back to the stored `encrypted:...` text,
+    /// this reports why the value could not be decrypted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EncryptionError::MissingPrivateKey`] if no private key is
+    /// found, [`EncryptionError::InvalidKey`] if it is malformed, and
+    /// [`EncryptionError::Decrypt`] if the value cannot be decrypted with it.
+    pub fn try_get(&self, reference: &str) -> Result<Option<String>, EncryptionError> {
+        let Some(value) = self.data.get(self.resolve_key(reference).as_ref()) else {
+            return Ok(None);
+        };
+        if !is_encrypted(value) {
+            return Ok(Some(value.clone()));
+        }
+        decrypt_with(self.private_key()?, value).map(Some)
+    }
+
+    /// Encrypt the value of a reference in place.
+    ///
+    /// If the file has no public key yet, a keypair is generated: the public
+    /// key is stored as [`PUBLIC_KEY_NAME`] and the private key is written
+    /// to the `.lenv.keys` file. Already encrypted values are left as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key does not exist, the public key is
+    /// malformed, or the `.lenv.keys` file cannot be written. Returns
+    /// [`EncryptionError::ExistingPrivateKey`] rather than generating a
+    /// keypair if a private key is already set in the environment or
+    /// `.lenv.keys`, since values encrypted for the new public key could not
+    /// be decrypted with it.
+    pub fn encrypt_key(&mut self, reference: &str) -> Result<&mut Self, EncryptionError> {
+        let value = self
+            .data
+            .get(reference)
+            .ok_or_else(|| EncryptionError::UnknownKey(reference.to_string()))?;
+        if is_encrypted(value) || reference == PUBLIC_KEY_NAME {
+            return Ok(self);
+        }
+
+        let public_key = if let Some(public_key) = self.data.get(PUBLIC_KEY_NAME) {
+            public_key.clone()
+        } else {
+            let mut keys = read_lino_env(keys_path(&self.file_path))?;
+            if env::var_os(PRIVATE_KEY_NAME).is_some() || keys.has(PRIVATE_KEY_NAME) {
+                return Err(EncryptionError::ExistingPrivateKey);
+            }
+            let (public_key, private_key) = generate_keypair();
+            keys.set(PRIVATE_KEY_NAME, &private_key).write()?;
+            self.private_key = OnceLock::new();
+            self.set(PUBLIC_KEY_NAME, &public_key);
+            public_key
+        };
+
+        let encrypted = encrypt_value(&public_key, &self.data[reference])?;
+        self.set(reference, &encrypted);
+        Ok(self)
+    }
+
+    /// Replace an encrypted value with its plaintext.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key does not exist, no private key is found,
+    /// or the value cannot be decrypted.
+    pub fn decrypt_key(&mut self, reference: &str) -> Result<&mut Self, EncryptionError> {
+        let value = self
+            .data
+            .get(reference)
+            .ok_or_else(|| EncryptionError::UnknownKey(reference.to_string()))?;
+        if !is_encrypted(value) {
+            return Ok(self);
+        }
+
+        let plaintext = decrypt_with(self.private_key()?, value)?;
+        self.set(reference, &plaintext);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{EnvGuard, TempLenv};
+    use std::fs;
+
+    #[test]
+    fn test_round_trip() {
+        let (public_key, private_key) = generate_keypair();
+        let encrypted = encrypt_value(&public_key, "hello").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(decrypt_value(&private_key, &encrypted).unwrap(), "hello");
+
+        let (_, other_private_key) = generate_keypair();
+        assert!(matches!(
+            decrypt_value(&other_private_key, &encrypted),
+            Err(EncryptionError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(matches!(
+            encrypt_value("abc", "hello"),
+            Err(EncryptionError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_key() {
+        let _guard = EnvGuard::new();
+        let file = TempLenv::new();
+        let path = file.path();
+        let mut env = LinoEnv::new(path);
+        env.set("TOKEN", "gh_secret").set("PLAIN", "value");
+        env.encrypt_key("TOKEN").unwrap();
+
+        assert!(is_encrypted(&env.data["TOKEN"]));
+        assert!(env.has(PUBLIC_KEY_NAME));
+        assert_eq!(env.get("TOKEN"), Some("gh_secret".to_string()));
+        assert!(read_lino_env(keys_path(path))
+            .unwrap()
+            .has(PRIVATE_KEY_NAME));
+
+        env.decrypt_key("TOKEN").unwrap();
+        assert_eq!(env.data["TOKEN"], "gh_secret");
+        assert!(matches!(
+            env.encrypt_key("MISSING"),
+            Err(EncryptionError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_keeps_existing_private_key() {
+        let mut guard = EnvGuard::new();
+        let file = TempLenv::new();
+        let path = file.path();
+        let (_, private_key) = generate_keypair();
+        let mut keys = LinoEnv::new(keys_path(path));
+        keys.set(PRIVATE_KEY_NAME, &private_key).write().unwrap();
+
+        let mut env = LinoEnv::new(path);
+        env.set("TOKEN", "gh_secret");
+        assert!(matches!(
+            env.encrypt_key("TOKEN"),
+            Err(EncryptionError::ExistingPrivateKey)
+        ));
+        assert_eq!(
+            read_lino_env(keys_path(path))
+                .unwrap()
+                .get(PRIVATE_KEY_NAME),
+            Some(private_key.clone())
+        );
+        assert_eq!(env.data["TOKEN"], "gh_secret");
+
+        fs::remove_file(keys_path(path)).unwrap();
+        guard.set(PRIVATE_KEY_NAME, &private_key);
+        assert!(matches!(
+            env.encrypt_key("TOKEN"),
+            Err(EncryptionError::ExistingPrivateKey)
+        ));
+        assert!(!std::path::Path::new(&keys_path(path)).exists());
+    }
+
+    #[test]
+    fn test_get_without_private_key_returns_stored_value() {
+        let _guard = EnvGuard::new();
+        let file = TempLenv::new();
+        let (public_key, private_key) = generate_keypair();
+        let encrypted = encrypt_value(&public_key, "hello").unwrap();
+        let mut env = LinoEnv::new(file.path());
+        env.set(PUBLIC_KEY_NAME, &public_key)
+            .set("TOKEN", &encrypted);
+
+        assert_eq!(env.get("TOKEN"), Some(encrypted));
+        assert!(matches!(
+            env.try_get("TOKEN"),
+            Err(EncryptionError::MissingPrivateKey)
+        ));
+        assert!(matches!(
+            env.decrypt_key("TOKEN"),
+            Err(EncryptionError::MissingPrivateKey)
+        ));
+        assert_eq!(env.try_get("MISSING").unwrap(), None);
+
+        // The keys file is picked up once it exists.
+        let mut keys = LinoEnv::new(keys_path(file.path()));
+        keys.set(PRIVATE_KEY_NAME, &private_key).write().unwrap();
+        assert_eq!(env.try_get("TOKEN").unwrap(), Some("hello".to_string()));
+        assert_eq!(env.get("TOKEN"), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_try_get_with_wrong_private_key() {
+        let mut guard = EnvGuard::new();
+        let (public_key, _) = generate_keypair();
+        let (_, other_private_key) = generate_keypair();
+        guard.set(PRIVATE_KEY_NAME, &other_private_key);
+        let mut env = LinoEnv::new(TempLenv::new());
+        env.set("TOKEN", &encrypt_value(&public_key, "hello").unwrap());
+
+        assert!(matches!(
+            env.try_get("TOKEN"),
+            Err(EncryptionError::Decrypt)
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+//! Generating and checking `.lenv.example` templates.
+//!
+//! An example file keeps the keys, comments and layout of a real `.lenv`
+//! file but replaces every value with a schema default or a placeholder, so
+//! it can be committed and kept in sync automatically.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::parser::{parse_str, Event};
+use crate::schema::{KeyType, Schema};
+use crate::LinoEnv;
+
+/// Placeholder value for a key, using its schema default or type when known.
+fn placeholder(key: &str, schema: Option<&Schema>) -> String {
+    let Some(spec) = schema.and_then(|schema| schema.get(key)) else {
+        return "<value>".to_string();
+    };
+    if let Some(default) = &spec.default {
+        return default.clone();
+    }
+    match &spec.key_type {
+        KeyType::Enum(values) => format!("<{}>", values.join("|")),
+        KeyType::Regex(_) => "<value>".to_string(),
+        key_type => format!("<{key_type}>"),
+    }
+}
+
+/// Derive example file content from `.lenv` source text.
+///
+/// Comments and blank lines are kept, each key is emitted once at the
+/// position of its first occurrence, and lines that do not parse are
+/// dropped. Keys declared in `schema` but absent from `source` are appended,
+/// preceded by their description.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::example::generate;
+///
+/// let example = generate("# GitHub access\nGITHUB_TOKEN: gh_abc123\n", None);
+/// assert_eq!(example, "# GitHub access\nGITHUB_TOKEN: <value>\n");
+/// ```
+#[must_use]
+pub fn generate(source: &str, schema: Option<&Schema>) -> String {
+    let mut output = String::new();
+    let mut seen = HashSet::new();
+
+    for event in parse_str(source) {
+        match event {
+            Event::Comment { text, .. } => {
+                output.push('#');
+                output.push_str(&text);
+                output.push('\n');
+            }
+            Event::BlankLine { .. } => output.push('\n'),
+            Event::Entry { key, .. } => {
+                if seen.insert(key.clone()) {
+                    let _ = writeln!(output, "{key}: {}", placeholder(&key, schema));
+                }
+            }
+            Event::Error { .. } => {}
+        }
+    }
+
+    push_schema_keys(&mut output, &seen, schema);
+    output
+}
+
+/// Append the keys of `schema` missing from `seen`, preceded by their
+/// descriptions.
+fn push_schema_keys(output: &mut String, seen: &HashSet<String>, schema: Option<&Schema>) {
+    for spec in schema.map_or(&[][..], Schema::keys) {
+        if seen.contains(&spec.name) {
+            continue;
+        }
+        if let Some(description) = &spec.description {
+            let _ = writeln!(output, "# {description}");
+        }
+        let _ = writeln!(output, "{}: {}", spec.name, placeholder(&spec.name, schema));
+    }
+}
+
+/// Derive example file content from a `LinoEnv`.
+///
+/// Keys, their order and comments are those of the environment in memory,
+/// as [`LinoEnv::write`] would write them, so keys set or deleted since it
+/// was read are accounted for. Keys declared in `schema` but absent from
+/// `env` are appended as in [`generate`].
+#[must_use]
+pub fn from_env(env: &LinoEnv, schema: Option<&Schema>) -> String {
+    let placeholders: HashMap<String, String> = env
+        .data
+        .keys()
+        .map(|key| (key.clone(), placeholder(key, schema)))
+        .collect();
+    let keys: Vec<&String> = env.layout.keys().iter().collect();
+    let mut out = Vec::new();
+    env.layout
+        .write_to(&mut out, &keys, &placeholders)
+        .expect("writing to a vector cannot fail");
+    let mut output = String::from_utf8(out).expect("written output is UTF-8");
+
+    let seen: HashSet<String> = placeholders.into_keys().collect();
+    push_schema_keys(&mut output, &seen, schema);
+    output
+}
+
+/// Key differences between an environment and its example file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExampleDrift {
+    /// Keys present in the environment but missing from the example.
+    pub missing_from_example: Vec<String>,
+    /// Keys present in the example but missing from the environment.
+    pub missing_from_env: Vec<String>,
+}
+
+impl ExampleDrift {
+    /// `true` if both files declare the same keys.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.missing_from_example.is_empty() && self.missing_from_env.is_empty()
+    }
+}
+
+/// Compare the keys of an environment and its example file.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::example::check;
+/// use lino_env::LinoEnv;
+///
+/// let mut env = LinoEnv::new(".lenv");
+/// env.set("A", "1").set("B", "2");
+/// let mut example = LinoEnv::new(".lenv.example");
+/// example.set("B", "<value>").set("C", "<value>");
+///
+/// let drift = check(&env, &example);
+/// assert_eq!(drift.missing_from_example, vec!["A".to_string()]);
+/// assert_eq!(drift.missing_from_env, vec!["C".to_string()]);
+/// ```
+#[must_use]
+pub fn check(env: &LinoEnv, example: &LinoEnv) -> ExampleDrift {
+    let missing = |from: &LinoEnv, to: &LinoEnv| {
+        let mut keys: Vec<String> = from.keys().into_iter().filter(|k| !to.has(k)).collect();
+        keys.sort();
+        keys
+    };
+
+    ExampleDrift {
+        missing_from_example: missing(env, example),
+        missing_from_env: missing(example, env),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempLenv;
+
+    #[test]
+    fn test_generate_preserves_layout() {
+        let source = "# Tokens\nGITHUB_TOKEN: gh_abc\n\nPORT: 80\nPORT: 81\nbroken\n";
+        assert_eq!(
+            generate(source, None),
+            "# Tokens\nGITHUB_TOKEN: <value>\n\nPORT: <value>\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_uses_schema() {
+        let schema = Schema::parse(
+            "PORT\n  type: int\n  default: 8080\nLEVEL\n  type: enum\n  values: a b\nURL\n  type: url\n  description: Service URL\n",
+        )
+        .unwrap();
+        assert_eq!(
+            generate("PORT: 80\nLEVEL: a\n", Some(&schema)),
+            "PORT: 8080\nLEVEL: <a|b>\n# Service URL\nURL: <url>\n"
+        );
+    }
+
+    #[test]
+    fn test_from_env_uses_memory() {
+        let file = TempLenv::with_contents("# Header\n\nA: 1\n# about D\nD: 4\nE: 5\n").unwrap();
+        let mut env = file.read().unwrap();
+        env.set("C", "3").set("B", "2").delete("D");
+
+        assert_eq!(
+            from_env(&env, None),
+            "# Header\n\nA: <value>\nE: <value>\nC: <value>\nB: <value>\n"
+        );
+
+        let schema = Schema::parse("URL\n  type: url\n  description: Service URL\n").unwrap();
+        let env = LinoEnv::new(TempLenv::new());
+        assert_eq!(from_env(&env, Some(&schema)), "# Service URL\nURL: <url>\n");
+    }
+
+    #[test]
+    fn test_check_in_sync() {
+        let mut env = LinoEnv::new(".lenv");
+        env.set("A", "1");
+        let mut example = LinoEnv::new(".lenv.example");
+        example.set("A", "<value>");
+        assert!(check(&env, &example).is_empty());
+    }
+}
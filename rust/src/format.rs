@@ -0,0 +1,216 @@
+//! Canonical formatting of `.lenv` files.
+//!
+//! The formatter is built on the same parser as [`LinoEnv::read`], so it
+//! never changes which keys a file defines. It writes every entry as
+//! `KEY: value` without whitespace around the key, writes multi-line values
+//! with [`format_entry`](crate::parser::format_entry), and keeps only the
+//! last value of a duplicated key, at the position of its first occurrence.
+//! Values are kept byte for byte, including surrounding whitespace; a value
+//! that cannot be written so that it reads back unchanged is a
+//! [`FormatError`] rather than being changed. Comments, blank lines between
+//! groups and lines the parser cannot interpret are kept.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::parser::{is_lossless, is_lossless_key, Parser};
+use crate::LinoEnv;
+
+/// Order of the keys in the formatted output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Keep the order of the file.
+    #[default]
+    Preserve,
+    /// Sort keys alphabetically.
+    Sorted,
+    /// Keep keys sharing the part before the separator together, groups in
+    /// order of first appearance, separated by blank lines.
+    GroupByPrefix(String),
+}
+
+/// Options of [`format_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Order of the keys.
+    pub order: KeyOrder,
+}
+
+/// Error returned when an entry cannot be formatted without changing it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    /// Key of the entry.
+    pub key: String,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` cannot be written so that it reads back unchanged",
+            self.key
+        )
+    }
+}
+
+impl Error for FormatError {}
+
+/// Format `.lenv` source with the default options.
+///
+/// # Errors
+///
+/// Returns a [`FormatError`] for the first entry whose key or value would
+/// read back differently once formatted.
+///
+/// # Examples
+///
+/// ```
+/// let source = "# Database\n  DB_HOST : localhost\nDB_PORT: 5432\nDB_HOST: db\n";
+/// assert_eq!(
+///     lino_env::format(source).unwrap(),
+///     "# Database\nDB_HOST: db\nDB_PORT: 5432\n"
+/// );
+/// ```
+pub fn format(source: &str) -> Result<String, FormatError> {
+    format_with(source, &FormatOptions::default())
+}
+
+/// Format `.lenv` source.
+///
+/// # Errors
+///
+/// Returns a [`FormatError`] for the first entry whose key or value would
+/// read back differently once formatted.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::format::{format_with, FormatOptions, KeyOrder};
+///
+/// let options = FormatOptions { order: KeyOrder::GroupByPrefix("_".to_string()) };
+/// let source = "DB_HOST: db\nAPI_URL: x\nDB_PORT: 5432\n";
+/// assert_eq!(
+///     format_with(source, &options).unwrap(),
+///     "DB_HOST: db\nDB_PORT: 5432\n\nAPI_URL: x\n"
+/// );
+/// ```
+pub fn format_with(source: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let mut env = LinoEnv::new("");
+    env.load(Parser::new(source.as_bytes()))
+        .expect("reading from a string cannot fail");
+
+    let mut keys: Vec<String> = env.keys();
+    if let Some(key) = keys
+        .iter()
+        .find(|key| !is_lossless_key(key) || !is_lossless(&env.data[*key]))
+    {
+        return Err(FormatError { key: key.clone() });
+    }
+    match &options.order {
+        KeyOrder::Preserve => {}
+        KeyOrder::Sorted => {
+            keys.sort();
+            for key in &keys {
+                env.layout.set_blank_before(key, false);
+            }
+        }
+        KeyOrder::GroupByPrefix(separator) => {
+            let group = |key: &str| {
+                key.split(separator.as_str())
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let mut first_seen: HashMap<String, usize> = HashMap::new();
+            for (index, key) in keys.iter().enumerate() {
+                first_seen.entry(group(key)).or_insert(index);
+            }
+            keys.sort_by_key(|key| first_seen[&group(key)]);
+            let mut previous = None;
+            for key in &keys {
+                let current = group(key);
+                env.layout
+                    .set_blank_before(key, previous.is_some_and(|previous| previous != current));
+                previous = Some(current);
+            }
+        }
+    }
+
+    let keys: Vec<&String> = keys.iter().collect();
+    let mut out = Vec::new();
+    env.layout
+        .write_to(&mut out, &keys, &env.data)
+        .expect("writing to a vector cannot fail");
+    Ok(String::from_utf8(out).expect("formatted output is UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizes_lines_and_keeps_values() {
+        let source =
+            "  A:   1   \n# note   \n\n\n\nB : two words \nbroken line   \nC: \"x  \ny\"\n";
+        assert_eq!(
+            format(source).unwrap(),
+            "A:   1   \n# note\n\nB: two words \nbroken line\nC:\n  x  \n  y\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_sections() {
+        let source = "A: 1\n\n#  Section B\n\nB: 2\n";
+        assert_eq!(format(source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "# header\n\nZ: 1\nA: \"\nmulti\n\"\n\n# section\n\n# lead\nM: 'a'\nM: b\n";
+        for options in [
+            FormatOptions::default(),
+            FormatOptions {
+                order: KeyOrder::Sorted,
+            },
+            FormatOptions {
+                order: KeyOrder::GroupByPrefix("_".to_string()),
+            },
+        ] {
+            let once = format_with(source, &options).unwrap();
+            assert_eq!(format_with(&once, &options).unwrap(), once, "{options:?}");
+        }
+    }
+
+    #[test]
+    fn test_duplicates_keep_last_value_and_comments() {
+        assert_eq!(
+            format("# first\nA: 1\nB: 2\n# second\nA: 3\n").unwrap(),
+            "# first\n# second\nA: 3\nB: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_sorted() {
+        let options = FormatOptions {
+            order: KeyOrder::Sorted,
+        };
+        assert_eq!(
+            format_with("# top\n\nB: 2\n\n# about A\nA: 1\n", &options).unwrap(),
+            "# top\n\n# about A\nA: 1\nB: 2\n"
+        );
+    }
+
+    #[test]
+    fn test_rejects_values_that_would_change() {
+        // An unterminated quote after a leading space is kept as written,
+        // but reads back without the space.
+        let source = "A:  \"open\nB: 2\n";
+        assert_eq!(
+            format(source),
+            Err(FormatError {
+                key: "A".to_string()
+            })
+        );
+    }
+}
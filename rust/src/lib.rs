@@ -5,45 +5,59 @@
 //!
 //! If a key appears multiple times in a file, the last value wins (rewrite semantics).
 
+pub mod build;
+#[cfg(feature = "clap")]
+pub mod clap;
+mod comments;
+pub mod config;
+pub mod diff;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod example;
+pub mod format;
+mod glob;
+pub mod lint;
+pub mod merge;
+pub mod node;
+pub mod parser;
+pub mod patch;
+pub mod permissions;
+pub mod policy;
+#[cfg(any(feature = "figment", feature = "config"))]
+pub mod provider;
+pub mod scan;
+pub mod schema;
+pub mod secret;
+pub mod source;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod view;
+
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, BufRead, BufReader};
 use std::path::Path;
 
+pub use config::LinoEnvConfig;
+pub use format::format;
+#[cfg(feature = "embed")]
+pub use lino_env_macros::include_lenv;
+#[cfg(feature = "derive")]
+pub use lino_env_macros::LinoEnvConfig;
+pub use merge::merge3;
+use parser::{Event, ParseErrorKind, Parser};
+use secret::{SecretValue, DEFAULT_SENSITIVE_PATTERNS, REDACTED};
+pub use source::{var, EnvSource};
+use zeroize::Zeroize;
+
+// Lets the macros of `lino-env-macros` refer to `::lino_env` inside this
+// crate.
+extern crate self as lino_env;
+
 /// Package version (matches Cargo.toml version).
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn read_multiline_quoted_value(
-    lines: &[&str],
-    start_index: usize,
-    value: &str,
-) -> Option<(String, usize)> {
-    let trimmed_value = value.trim_start();
-    let quote = trimmed_value.chars().next()?;
-    if !matches!(quote, '"' | '\'') {
-        return None;
-    }
-
-    let first_part = &trimmed_value[quote.len_utf8()..];
-
-    // Preserve existing single-line quoted value behavior.
-    if first_part.contains(quote) {
-        return None;
-    }
-
-    let mut parts = vec![first_part.to_string()];
-    for (line_index, line) in lines.iter().enumerate().skip(start_index + 1) {
-        if let Some(closing_quote_index) = line.find(quote) {
-            parts.push(line[..closing_quote_index].to_string());
-            return Some((parts.join("\n"), line_index));
-        }
-
-        parts.push((*line).to_string());
-    }
-
-    None
-}
-
 /// `LinoEnv` - A struct to read and write `.lenv` files.
 ///
 /// `.lenv` files use `: ` instead of `=` for key-value separation.
@@ -71,10 +85,20 @@ fn read_multiline_quoted_value(
 /// // Clean up
 /// fs::remove_file(path).ok();
 /// ```
-#[derive(Debug, Clone)]
+///
+/// Values of sensitive keys (see [`LinoEnv::set_sensitive_patterns`]) are
+/// printed as `***` by `Debug`, and all values are zeroized when they are
+/// overwritten, deleted or dropped.
+#[derive(Clone)]
 pub struct LinoEnv {
     file_path: String,
     data: HashMap<String, String>,
+    layout: comments::Layout,
+    sensitive_patterns: Vec<String>,
+    file_mode: u32,
+    key_policy: policy::KeyPolicy,
+    #[cfg(feature = "encryption")]
+    private_key: std::sync::OnceLock<crypto_box::SecretKey>,
 }
 
 impl LinoEnv {
@@ -95,6 +119,15 @@ impl LinoEnv {
         Self {
             file_path: file_path.as_ref().to_string(),
             data: HashMap::new(),
+            layout: comments::Layout::default(),
+            sensitive_patterns: DEFAULT_SENSITIVE_PATTERNS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            file_mode: permissions::DEFAULT_FILE_MODE,
+            key_policy: policy::KeyPolicy::default(),
+            #[cfg(feature = "encryption")]
+            private_key: std::sync::OnceLock::new(),
         }
     }
 
@@ -115,49 +148,97 @@ impl LinoEnv {
     /// let _ = env.read();
     /// ```
     pub fn read(&mut self) -> io::Result<&mut Self> {
-        self.data.clear();
+        self.clear_data();
 
         let path = Path::new(&self.file_path);
         if !path.exists() {
             return Ok(self);
         }
 
-        let content = fs::read_to_string(path)?;
-        let lines: Vec<&str> = content.lines().collect();
-        let mut line_index = 0;
-        while line_index < lines.len() {
-            let line = lines[line_index];
-            let trimmed = line.trim();
-
-            // Skip empty lines and comments
-            if trimmed.is_empty() || trimmed.starts_with('#') {
-                line_index += 1;
-                continue;
-            }
+        self.read_from(BufReader::new(fs::File::open(path)?))
+    }
 
-            // Parse line with `: ` separator
-            if let Some(separator_index) = line.find(": ") {
-                let key = line[..separator_index].trim().to_string();
-                let mut value = line[separator_index + 2..].to_string(); // Don't trim value to preserve spaces
-                if let Some((multiline_value, next_line_index)) =
-                    read_multiline_quoted_value(&lines, line_index, &value)
-                {
-                    value = multiline_value;
-                    line_index = next_line_index;
-                }
+    /// Parse `.lenv` content from any reader, replacing the current data.
+    ///
+    /// Lines the parser cannot interpret define no key, but are kept in
+    /// place so that [`LinoEnv::write`] does not drop them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reader fails or yields invalid UTF-8, or an
+    /// [`io::ErrorKind::InvalidData`] error if a key is rejected by the
+    /// [`KeyPolicy`](policy::KeyPolicy).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.read_from("KEY: value\n".as_bytes()).unwrap();
+    /// assert_eq!(env.get("KEY"), Some("value".to_string()));
+    /// ```
+    pub fn read_from<R: BufRead>(&mut self, reader: R) -> io::Result<&mut Self> {
+        self.load(Parser::new(reader))
+    }
 
-                // Last value wins (rewrite semantics)
-                self.data.insert(key, value);
+    /// Replace the current data with parsed `events`, keeping lines without
+    /// a separator in the layout.
+    fn load<I>(&mut self, events: I) -> io::Result<&mut Self>
+    where
+        I: IntoIterator<Item = io::Result<Event>>,
+    {
+        self.clear_data();
+
+        let mut comments = comments::CommentCollector::default();
+        for event in events {
+            match event? {
+                Event::Entry { key, value, span } => {
+                    let key = self
+                        .key_policy
+                        .check(&key)
+                        .map_err(|error| error.at_line(span.start_line))?;
+                    let key = self.resolve_key(&key).into_owned();
+                    comments.entry(&mut self.layout, &key);
+                    // Last value wins (rewrite semantics)
+                    if let Some(mut old) = self.data.insert(key, value) {
+                        old.zeroize();
+                    }
+                }
+                Event::Comment { indent, text, .. } => comments.comment(&indent, &text),
+                Event::BlankLine { .. } => comments.blank_line(&mut self.layout),
+                Event::Error {
+                    kind: ParseErrorKind::MissingSeparator,
+                    text,
+                    ..
+                } => comments.invalid(&text),
+                Event::Error { .. } => {}
             }
-
-            line_index += 1;
         }
+        comments.finish(&mut self.layout);
 
         Ok(self)
     }
 
+    /// Path of the .lenv file this instance reads from and writes to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    /// let env = LinoEnv::new(".lenv");
+    /// assert_eq!(env.file_path(), ".lenv");
+    /// ```
+    #[must_use]
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
     /// Get the value of a reference (key).
     ///
+    /// With the `encryption` feature, `encrypted:...` values are decrypted
+    /// transparently when the private key is available; otherwise the stored
+    /// value is returned unchanged. `try_get` returns an error instead.
+    ///
     /// # Arguments
     ///
     /// * `reference` - The key to look up
@@ -177,7 +258,60 @@ impl LinoEnv {
     /// ```
     #[must_use]
     pub fn get(&self, reference: &str) -> Option<String> {
-        self.data.get(reference).cloned()
+        let value = self.data.get(self.resolve_key(reference).as_ref())?;
+        #[cfg(feature = "encryption")]
+        if let Some(plaintext) = self.decrypted(value) {
+            return Some(plaintext);
+        }
+        Some(value.clone())
+    }
+
+    /// Get the value of a reference wrapped in a [`SecretValue`].
+    ///
+    /// The returned value prints as `***` and is zeroized when dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set("API_KEY", "abc");
+    /// let secret = env.get_secret("API_KEY").unwrap();
+    /// assert_eq!(secret.to_string(), "***");
+    /// assert_eq!(secret.expose(), "abc");
+    /// ```
+    #[must_use]
+    pub fn get_secret(&self, reference: &str) -> Option<SecretValue> {
+        self.get(reference).map(SecretValue::new)
+    }
+
+    /// Replace the glob patterns marking keys as sensitive.
+    ///
+    /// `*` matches any sequence and matching ignores ASCII case. The default
+    /// is `*_TOKEN`, `*_SECRET` and `*_PASSWORD`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set_sensitive_patterns(&["*_KEY", "DATABASE_URL"]);
+    /// env.set("API_KEY", "abc");
+    /// assert!(env.is_sensitive("API_KEY"));
+    /// assert!(!env.is_sensitive("GITHUB_TOKEN"));
+    /// assert!(!format!("{env:?}").contains("abc"));
+    /// ```
+    pub fn set_sensitive_patterns<S: AsRef<str>>(&mut self, patterns: &[S]) -> &mut Self {
+        self.sensitive_patterns = patterns.iter().map(|p| p.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Check if a reference matches one of the sensitive key patterns.
+    #[must_use]
+    pub fn is_sensitive(&self, reference: &str) -> bool {
+        self.sensitive_patterns
+            .iter()
+            .any(|pattern| secret::matches_pattern(pattern, reference))
     }
 
     /// Set a reference to a value.
@@ -187,6 +321,12 @@ impl LinoEnv {
     /// * `reference` - The key to set
     /// * `value` - The new value
     ///
+    /// # Panics
+    ///
+    /// Panics if the [`KeyPolicy`](policy::KeyPolicy) rejects the key; the
+    /// default policy accepts any key. Use [`LinoEnv::try_set`] for keys
+    /// that may be rejected.
+    ///
     /// # Examples
     ///
     /// ```
@@ -196,15 +336,24 @@ impl LinoEnv {
     /// assert_eq!(env.get("KEY"), Some("value".to_string()));
     /// ```
     pub fn set(&mut self, reference: &str, value: &str) -> &mut Self {
-        self.data.insert(reference.to_string(), value.to_string());
+        if let Err(error) = self.try_set(reference, value) {
+            panic!("{error}; use `try_set` for keys that may be rejected");
+        }
         self
     }
 
     /// Write the current data back to the .lenv file.
     ///
+    /// A new file is created with mode `0600` on Unix (see
+    /// [`LinoEnv::set_file_mode`]); an existing file keeps its permissions.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the file cannot be written.
+    /// Returns an error if the file cannot be written, or an
+    /// [`io::ErrorKind::InvalidData`] error if a key is rejected by the
+    /// [`KeyPolicy`](policy::KeyPolicy) or a key or value cannot be written
+    /// so that it reads back unchanged (see [`parser::is_lossless_key`] and
+    /// [`parser::is_lossless`]).
     ///
     /// # Examples
     ///
@@ -222,11 +371,41 @@ impl LinoEnv {
     /// fs::remove_file(path).ok();
     /// ```
     pub fn write(&self) -> io::Result<&Self> {
-        let mut file = fs::File::create(&self.file_path)?;
-
-        for (key, value) in &self.data {
-            writeln!(file, "{key}: {value}")?;
+        self.check_keys()?;
+        if let Some(key) = self
+            .layout
+            .keys()
+            .iter()
+            .find(|key| !parser::is_lossless_key(key))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the key {key:?} cannot be written so that it reads back unchanged"),
+            ));
+        }
+        if let Some(key) = self.layout.keys().iter().find(|key| {
+            self.data
+                .get(*key)
+                .is_some_and(|value| !parser::is_lossless(value))
+        }) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the value of `{key}` cannot be written so that it reads back unchanged"),
+            ));
         }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, self.file_mode);
+        let mut file = options.open(&self.file_path)?;
+
+        #[allow(unused_mut)]
+        let mut keys: Vec<&String> = self.layout.keys().iter().collect();
+        // The public key goes first, right after the header comment.
+        #[cfg(feature = "encryption")]
+        keys.sort_by_key(|key| key.as_str() != encryption::PUBLIC_KEY_NAME);
+
+        self.layout.write_to(&mut file, &keys, &self.data)?;
 
         Ok(self)
     }
@@ -252,7 +431,7 @@ impl LinoEnv {
     /// ```
     #[must_use]
     pub fn has(&self, reference: &str) -> bool {
-        self.data.contains_key(reference)
+        self.data.contains_key(self.resolve_key(reference).as_ref())
     }
 
     /// Delete a reference.
@@ -271,7 +450,11 @@ impl LinoEnv {
     /// assert!(!env.has("KEY"));
     /// ```
     pub fn delete(&mut self, reference: &str) -> &mut Self {
-        self.data.remove(reference);
+        let key = self.resolve_key(reference).into_owned();
+        self.layout.remove(&key);
+        if let Some(mut old) = self.data.remove(&key) {
+            old.zeroize();
+        }
         self
     }
 
@@ -279,7 +462,8 @@ impl LinoEnv {
     ///
     /// # Returns
     ///
-    /// A vector of all keys in the environment.
+    /// A vector of all keys in the environment, in file order; keys added
+    /// with [`LinoEnv::set`] come after the keys read from the file.
     ///
     /// # Examples
     ///
@@ -294,7 +478,7 @@ impl LinoEnv {
     /// ```
     #[must_use]
     pub fn keys(&self) -> Vec<String> {
-        self.data.keys().cloned().collect()
+        self.layout.keys().to_vec()
     }
 
     /// Get all entries as a `HashMap`.
@@ -319,6 +503,48 @@ impl LinoEnv {
     }
 }
 
+impl LinoEnv {
+    fn clear_data(&mut self) {
+        for value in self.data.values_mut() {
+            value.zeroize();
+        }
+        self.data.clear();
+        self.layout.clear();
+    }
+}
+
+impl fmt::Debug for LinoEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Data<'a>(&'a LinoEnv);
+
+        impl fmt::Debug for Data<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.0.data.iter().map(|(key, value)| {
+                        let shown = if self.0.is_sensitive(key) {
+                            REDACTED
+                        } else {
+                            value.as_str()
+                        };
+                        (key, shown)
+                    }))
+                    .finish()
+            }
+        }
+
+        f.debug_struct("LinoEnv")
+            .field("file_path", &self.file_path)
+            .field("data", &Data(self))
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for LinoEnv {
+    fn drop(&mut self) {
+        self.clear_data();
+    }
+}
+
 /// Convenience function to read a .lenv file.
 ///
 /// # Arguments
@@ -383,348 +609,4 @@ pub fn write_lino_env<P: AsRef<str>>(
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-
-    fn cleanup(path: &str) {
-        fs::remove_file(path).ok();
-    }
-
-    fn test_file(name: &str) -> String {
-        std::env::temp_dir()
-            .join(format!("lino_env_test_{name}.lenv"))
-            .to_string_lossy()
-            .to_string()
-    }
-
-    mod basic_tests {
-        use super::*;
-
-        #[test]
-        fn test_create_and_write() {
-            let test_file = test_file("basic_create_write");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("GITHUB_TOKEN", "gh_test123");
-            env.set("TELEGRAM_TOKEN", "054test456");
-            env.write().unwrap();
-
-            assert!(Path::new(&test_file).exists());
-            cleanup(&test_file);
-        }
-
-        #[test]
-        fn test_read() {
-            let test_file = test_file("basic_read");
-            cleanup(&test_file);
-            // First create a file
-            let mut env1 = LinoEnv::new(&test_file);
-            env1.set("GITHUB_TOKEN", "gh_test123");
-            env1.set("TELEGRAM_TOKEN", "054test456");
-            env1.write().unwrap();
-
-            // Then read it
-            let mut env2 = LinoEnv::new(&test_file);
-            env2.read().unwrap();
-
-            assert_eq!(env2.get("GITHUB_TOKEN"), Some("gh_test123".to_string()));
-            assert_eq!(env2.get("TELEGRAM_TOKEN"), Some("054test456".to_string()));
-            cleanup(&test_file);
-        }
-    }
-
-    mod get_tests {
-        use super::*;
-
-        #[test]
-        fn test_get_value() {
-            let test_file = test_file("get_value");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("API_KEY", "value1");
-
-            assert_eq!(env.get("API_KEY"), Some("value1".to_string()));
-            cleanup(&test_file);
-        }
-
-        #[test]
-        fn test_get_nonexistent() {
-            let test_file = test_file("get_nonexistent");
-            let env = LinoEnv::new(&test_file);
-            assert_eq!(env.get("NON_EXISTENT"), None);
-        }
-    }
-
-    mod set_tests {
-        use super::*;
-
-        #[test]
-        fn test_set_overwrites() {
-            let test_file = test_file("set_overwrites");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("API_KEY", "value1");
-            env.set("API_KEY", "new_value");
-
-            assert_eq!(env.get("API_KEY"), Some("new_value".to_string()));
-            cleanup(&test_file);
-        }
-    }
-
-    mod duplicate_key_tests {
-        use super::*;
-
-        #[test]
-        fn test_duplicate_keys_last_value_wins() {
-            let test_file = test_file("duplicate_keys");
-            cleanup(&test_file);
-            // Write a file with duplicate keys manually
-            fs::write(&test_file, "A: value1\nA: value2\n").unwrap();
-
-            let mut env = LinoEnv::new(&test_file);
-            env.read().unwrap();
-
-            assert_eq!(env.get("A"), Some("value2".to_string()));
-            cleanup(&test_file);
-        }
-    }
-
-    mod has_tests {
-        use super::*;
-
-        #[test]
-        fn test_has_existing() {
-            let test_file = test_file("has_existing");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("KEY", "value");
-
-            assert!(env.has("KEY"));
-            cleanup(&test_file);
-        }
-
-        #[test]
-        fn test_has_nonexistent() {
-            let test_file = test_file("has_nonexistent");
-            let env = LinoEnv::new(&test_file);
-            assert!(!env.has("NON_EXISTENT"));
-        }
-    }
-
-    mod delete_tests {
-        use super::*;
-
-        #[test]
-        fn test_delete() {
-            let test_file = test_file("delete");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("KEY", "value1");
-            env.delete("KEY");
-
-            assert!(!env.has("KEY"));
-            assert_eq!(env.get("KEY"), None);
-            cleanup(&test_file);
-        }
-    }
-
-    mod keys_tests {
-        use super::*;
-
-        #[test]
-        fn test_keys() {
-            let test_file = test_file("keys");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("KEY1", "value1");
-            env.set("KEY2", "value2");
-            env.set("KEY3", "value3");
-
-            let keys = env.keys();
-            assert!(keys.contains(&"KEY1".to_string()));
-            assert!(keys.contains(&"KEY2".to_string()));
-            assert!(keys.contains(&"KEY3".to_string()));
-            assert_eq!(keys.len(), 3);
-            cleanup(&test_file);
-        }
-    }
-
-    mod to_hash_map_tests {
-        use super::*;
-
-        #[test]
-        fn test_to_hash_map() {
-            let test_file = test_file("to_hash_map");
-            cleanup(&test_file);
-            let mut env = LinoEnv::new(&test_file);
-            env.set("KEY1", "value1");
-            env.set("KEY2", "value2");
-
-            let obj = env.to_hash_map();
-            assert_eq!(obj.get("KEY1"), Some(&"value1".to_string()));
-            assert_eq!(obj.get("KEY2"), Some(&"value2".to_string()));
-            cleanup(&test_file);
-        }
-    }
-
-    mod persistence_tests {
-        use super::*;
-
-        #[test]
-        fn test_persist_values() {
-            let test_file = test_file("persist_values");
-            cleanup(&test_file);
-            let mut env1 = LinoEnv::new(&test_file);
-            env1.set("KEY", "value");
-            env1.write().unwrap();
-
-            let mut env2 = LinoEnv::new(&test_file);
-            env2.read().unwrap();
-
-            assert_eq!(env2.get("KEY"), Some("value".to_string()));
-            cleanup(&test_file);
-        }
-    }
-
-    mod convenience_function_tests {
-        use super::*;
-
-        #[test]
-        fn test_read_lino_env() {
-            let test_file_path = test_file("convenience_read");
-            cleanup(&test_file_path);
-            let mut data = HashMap::new();
-            data.insert("GITHUB_TOKEN".to_string(), "gh_test".to_string());
-            data.insert("TELEGRAM_TOKEN".to_string(), "054test".to_string());
-            write_lino_env(&test_file_path, &data).unwrap();
-
-            let env = read_lino_env(&test_file_path).unwrap();
-            assert_eq!(env.get("GITHUB_TOKEN"), Some("gh_test".to_string()));
-            assert_eq!(env.get("TELEGRAM_TOKEN"), Some("054test".to_string()));
-            cleanup(&test_file_path);
-        }
-
-        #[test]
-        fn test_write_lino_env() {
-            let test_file_path = test_file("convenience_write");
-            cleanup(&test_file_path);
-            let mut data = HashMap::new();
-            data.insert("API_KEY".to_string(), "test_key".to_string());
-            data.insert("SECRET".to_string(), "test_secret".to_string());
-            write_lino_env(&test_file_path, &data).unwrap();
-
-            let env = read_lino_env(&test_file_path).unwrap();
-            assert_eq!(env.get("API_KEY"), Some("test_key".to_string()));
-            assert_eq!(env.get("SECRET"), Some("test_secret".to_string()));
-            cleanup(&test_file_path);
-        }
-    }
-
-    mod format_tests {
-        use super::*;
-
-        #[test]
-        fn test_values_with_colons() {
-            let test_file_path = test_file("format_colons");
-            cleanup(&test_file_path);
-            let mut env = LinoEnv::new(&test_file_path);
-            env.set("URL", "https://example.com:8080");
-            env.write().unwrap();
-
-            let mut env2 = LinoEnv::new(&test_file_path);
-            env2.read().unwrap();
-            assert_eq!(
-                env2.get("URL"),
-                Some("https://example.com:8080".to_string())
-            );
-            cleanup(&test_file_path);
-        }
-
-        #[test]
-        fn test_values_with_spaces() {
-            let test_file_path = test_file("format_spaces");
-            cleanup(&test_file_path);
-            let mut env = LinoEnv::new(&test_file_path);
-            env.set("MESSAGE", "Hello World");
-            env.write().unwrap();
-
-            let mut env2 = LinoEnv::new(&test_file_path);
-            env2.read().unwrap();
-            assert_eq!(env2.get("MESSAGE"), Some("Hello World".to_string()));
-            cleanup(&test_file_path);
-        }
-    }
-
-    mod multiline_quoted_value_tests {
-        use super::*;
-
-        #[test]
-        fn test_multi_line_double_quoted_values() {
-            let test_file_path = test_file("multiline_double_quoted");
-            cleanup(&test_file_path);
-            fs::write(
-                &test_file_path,
-                "HIVE_TELEGRAM_BOT_CONFIGURATION: \"\nTELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true\n\"\nAFTER: value\n",
-            )
-            .unwrap();
-
-            let mut env = LinoEnv::new(&test_file_path);
-            env.read().unwrap();
-
-            assert_eq!(
-                env.get("HIVE_TELEGRAM_BOT_CONFIGURATION"),
-                Some(
-                    "\nTELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true\n"
-                        .to_string()
-                )
-            );
-            assert_eq!(env.get("TELEGRAM_BOT_TOKEN"), None);
-            assert_eq!(env.get("AFTER"), Some("value".to_string()));
-            cleanup(&test_file_path);
-        }
-
-        #[test]
-        fn test_multi_line_single_quoted_values() {
-            let test_file_path = test_file("multiline_single_quoted");
-            cleanup(&test_file_path);
-            fs::write(&test_file_path, "SCRIPT: 'line1\nline2'\nAFTER: value\n").unwrap();
-
-            let mut env = LinoEnv::new(&test_file_path);
-            env.read().unwrap();
-
-            assert_eq!(env.get("SCRIPT"), Some("line1\nline2".to_string()));
-            assert_eq!(env.get("AFTER"), Some("value".to_string()));
-            cleanup(&test_file_path);
-        }
-    }
-
-    mod edge_case_tests {
-        use super::*;
-
-        #[test]
-        fn test_nonexistent_file() {
-            let test_file_path = test_file("nonexistent");
-            cleanup(&test_file_path);
-            let mut env = LinoEnv::new(&test_file_path);
-            env.read().unwrap();
-
-            assert_eq!(env.get("ANY_KEY"), None);
-            assert!(env.keys().is_empty());
-        }
-
-        #[test]
-        fn test_empty_values() {
-            let test_file_path = test_file("empty_values");
-            cleanup(&test_file_path);
-            let mut env = LinoEnv::new(&test_file_path);
-            env.set("EMPTY_KEY", "");
-            env.write().unwrap();
-
-            let mut env2 = LinoEnv::new(&test_file_path);
-            env2.read().unwrap();
-            assert_eq!(env2.get("EMPTY_KEY"), Some(String::new()));
-            cleanup(&test_file_path);
-        }
-    }
-}
+mod tests;
@@ -0,0 +1,443 @@
+//! Style and correctness checks for `.lenv` files.
+//!
+//! [`LintConfig::lint`] runs every enabled [`Rule`] over a file and returns
+//! [`Diagnostic`]s with the rule, a [`Severity`] and the [`Span`] of the
+//! offending text. Severities can be changed or rules turned off per project
+//! with a config file in `.lenv` syntax:
+//!
+//! ```text
+//! # .lenvlint
+//! empty-value: off
+//! trailing-whitespace: error
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use lino_env::lint::{lint, Rule, Severity};
+//!
+//! let diagnostics = lint("API_URL=https://example.com\nTOKEN: \n");
+//! assert_eq!(diagnostics[0].rule, Rule::DotenvSyntax);
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! assert_eq!(diagnostics[1].rule, Rule::EmptyValue);
+//! assert_eq!(diagnostics[1].span.start_line, 2);
+//! assert_eq!(diagnostics[2].rule, Rule::TrailingWhitespace);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::parser::{parse_str, Event, ParseErrorKind, Span};
+use crate::policy::is_posix_name;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Likely a mistake, but the file is read as intended.
+    Warning,
+    /// The file is not read as its author probably intended.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A lint check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// A key is defined more than once; only the last value is used.
+    DuplicateKey,
+    /// A line is neither a comment nor a `key: value` entry.
+    MissingSeparator,
+    /// A `.env`-style `KEY=value` line, which is not read as an entry.
+    DotenvSyntax,
+    /// A line ends with spaces or tabs.
+    TrailingWhitespace,
+    /// A quoted value is never closed.
+    UnterminatedQuote,
+    /// A key has an empty value.
+    EmptyValue,
+    /// A key is not a POSIX environment variable name.
+    NonPosixKey,
+}
+
+impl Rule {
+    /// All rules.
+    pub const ALL: [Self; 7] = [
+        Self::DuplicateKey,
+        Self::MissingSeparator,
+        Self::DotenvSyntax,
+        Self::TrailingWhitespace,
+        Self::UnterminatedQuote,
+        Self::EmptyValue,
+        Self::NonPosixKey,
+    ];
+
+    /// Stable identifier, used in config files and reports.
+    #[must_use]
+    pub const fn id(self) -> &'static str {
+        match self {
+            Self::DuplicateKey => "duplicate-key",
+            Self::MissingSeparator => "missing-separator",
+            Self::DotenvSyntax => "dotenv-syntax",
+            Self::TrailingWhitespace => "trailing-whitespace",
+            Self::UnterminatedQuote => "unterminated-quote",
+            Self::EmptyValue => "empty-value",
+            Self::NonPosixKey => "non-posix-key",
+        }
+    }
+
+    /// Human-readable description.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::DuplicateKey => "key defined more than once",
+            Self::MissingSeparator => "line without `: ` separator",
+            Self::DotenvSyntax => "`.env`-style `KEY=value` line",
+            Self::TrailingWhitespace => "trailing whitespace",
+            Self::UnterminatedQuote => "quoted value never closed",
+            Self::EmptyValue => "empty value",
+            Self::NonPosixKey => "key is not a POSIX environment variable name",
+        }
+    }
+
+    /// Severity used unless the config changes it.
+    #[must_use]
+    pub const fn default_severity(self) -> Severity {
+        match self {
+            Self::MissingSeparator | Self::DotenvSyntax | Self::UnterminatedQuote => {
+                Severity::Error
+            }
+            Self::DuplicateKey
+            | Self::TrailingWhitespace
+            | Self::EmptyValue
+            | Self::NonPosixKey => Severity::Warning,
+        }
+    }
+
+    /// Look up a rule by its [`id`](Rule::id).
+    #[must_use]
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+}
+
+/// A problem found by the linter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Rule that found the problem.
+    pub rule: Rule,
+    /// Configured severity of the rule.
+    pub severity: Severity,
+    /// Location of the offending text.
+    pub span: Span,
+    /// Description of this occurrence.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {}[{}]: {}",
+            self.span.start_line,
+            self.severity,
+            self.rule.id(),
+            self.message
+        )
+    }
+}
+
+/// Error returned when reading a [`LintConfig`].
+#[derive(Debug)]
+pub enum LintConfigError {
+    /// The config file could not be read.
+    Io(io::Error),
+    /// The config names an unknown rule or severity.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for LintConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for LintConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for LintConfigError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Which rules run and with what severity.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    overrides: HashMap<Rule, Option<Severity>>,
+}
+
+/// Lint `source` with every rule at its default severity.
+#[must_use]
+pub fn lint(source: &str) -> Vec<Diagnostic> {
+    LintConfig::new().lint(source)
+}
+
+/// `true` if `line` looks like `KEY=value`, optionally after `export `.
+fn is_dotenv_line(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    line.split_once('=')
+        .is_some_and(|(key, _)| is_posix_name(key.trim_end()))
+}
+
+fn line_spans(source: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut start = 0;
+    source
+        .split_inclusive('\n')
+        .enumerate()
+        .map(move |(index, line)| {
+            let line_start = start;
+            start += line.len();
+            let text = line.strip_suffix('\n').unwrap_or(line);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            (index + 1, line_start, text)
+        })
+}
+
+impl LintConfig {
+    /// All rules at their default severity.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change the severity of `rule`, or turn it off with `None`.
+    pub fn set(&mut self, rule: Rule, severity: Option<Severity>) -> &mut Self {
+        self.overrides.insert(rule, severity);
+        self
+    }
+
+    /// Configured severity of `rule`, or `None` if it is turned off.
+    #[must_use]
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.overrides
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| Some(rule.default_severity()))
+    }
+
+    /// Parse a config of `rule-id: off|warning|error` lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LintConfigError::Parse`] for unknown rules or severities.
+    pub fn parse(source: &str) -> Result<Self, LintConfigError> {
+        let mut config = Self::new();
+        for event in parse_str(source) {
+            let (key, value, span) = match event {
+                Event::Entry { key, value, span } => (key, value, span),
+                Event::Error { text, span, .. } => {
+                    return Err(LintConfigError::Parse {
+                        line: span.start_line,
+                        message: format!("expected `rule: severity`, found `{}`", text.trim()),
+                    })
+                }
+                _ => continue,
+            };
+            let error = |message: String| LintConfigError::Parse {
+                line: span.start_line,
+                message,
+            };
+            let rule = Rule::from_id(&key).ok_or_else(|| error(format!("unknown rule `{key}`")))?;
+            let severity = match value.trim() {
+                "off" => None,
+                "warning" => Some(Severity::Warning),
+                "error" => Some(Severity::Error),
+                other => {
+                    return Err(error(format!(
+                        "unknown severity `{other}` (expected off, warning or error)"
+                    )))
+                }
+            };
+            config.set(rule, severity);
+        }
+        Ok(config)
+    }
+
+    /// Read a config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, LintConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Run the enabled rules over `source`, in file order.
+    #[must_use]
+    pub fn lint(&self, source: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut report = |rule: Rule, span: Span, message: String| {
+            if let Some(severity) = self.severity(rule) {
+                diagnostics.push(Diagnostic {
+                    rule,
+                    severity,
+                    span,
+                    message,
+                });
+            }
+        };
+
+        for (line, start, text) in line_spans(source) {
+            let trimmed = text.trim_end_matches([' ', '\t']);
+            if trimmed.len() < text.len() {
+                let span = Span {
+                    start_line: line,
+                    end_line: line,
+                    start: start + trimmed.len(),
+                    end: start + text.len(),
+                };
+                report(
+                    Rule::TrailingWhitespace,
+                    span,
+                    "line ends with whitespace".to_string(),
+                );
+            }
+        }
+
+        let mut defined: HashMap<String, usize> = HashMap::new();
+        for event in parse_str(source) {
+            match event {
+                Event::Entry { key, value, span } => {
+                    if let Some(previous) = defined.insert(key.clone(), span.start_line) {
+                        report(
+                            Rule::DuplicateKey,
+                            span,
+                            format!("`{key}` is already defined on line {previous}; the last value wins"),
+                        );
+                    }
+                    if value.trim().is_empty() {
+                        report(
+                            Rule::EmptyValue,
+                            span,
+                            format!("`{key}` has an empty value"),
+                        );
+                    }
+                    if !is_posix_name(&key) {
+                        report(
+                            Rule::NonPosixKey,
+                            span,
+                            format!("`{key}` is not a valid environment variable name"),
+                        );
+                    }
+                }
+                Event::Error {
+                    kind: ParseErrorKind::UnterminatedQuote,
+                    span,
+                    ..
+                } => report(
+                    Rule::UnterminatedQuote,
+                    span,
+                    "quoted value is never closed; it is read as a single line".to_string(),
+                ),
+                Event::Error { text, span, .. } if is_dotenv_line(&text) => report(
+                    Rule::DotenvSyntax,
+                    span,
+                    "`KEY=value` is not an entry; write `KEY: value`".to_string(),
+                ),
+                Event::Error { text, span, .. } => {
+                    let message = if text.contains(':') {
+                        "line has no `: ` separator; add a space after `:`"
+                    } else {
+                        "line has no `: ` separator and is ignored"
+                    };
+                    report(Rule::MissingSeparator, span, message.to_string());
+                }
+                Event::Comment { .. } | Event::BlankLine { .. } => {}
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.span.start_line, diagnostic.span.start));
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> Vec<(Rule, usize)> {
+        lint(source)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.span.start_line))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(
+            rules("A: 1\nA: 2\nbroken\nB:3\nexport C=4\nD: \"open\nmy key: x\n"),
+            vec![
+                (Rule::DuplicateKey, 2),
+                (Rule::MissingSeparator, 3),
+                (Rule::MissingSeparator, 4),
+                (Rule::DotenvSyntax, 5),
+                (Rule::UnterminatedQuote, 6),
+                (Rule::NonPosixKey, 7),
+            ]
+        );
+        assert!(lint("# fine\n\nA: 1\n").is_empty());
+    }
+
+    #[test]
+    fn test_trailing_whitespace_span() {
+        let diagnostics = lint("A: 1  \r\nB: 2\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].span.start, diagnostics[0].span.end), (4, 6));
+    }
+
+    #[test]
+    fn test_config() {
+        let config =
+            LintConfig::parse("# project rules\nempty-value: off\nduplicate-key: error\n").unwrap();
+        let diagnostics = config.lint("A: \nA: 1\n");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].rule, Rule::TrailingWhitespace);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "line 2: error[duplicate-key]: `A` is already defined on line 1; the last value wins"
+        );
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let error = LintConfig::parse("no-such-rule: off\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: unknown rule `no-such-rule`");
+        let error = LintConfig::parse("empty-value: loud\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: unknown severity `loud` (expected off, warning or error)"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! Key-level three-way merge of `.lenv` files.
+//!
+//! Each key is merged on its own: a change made on only one side wins, and a
+//! key changed differently on both sides is a [`Conflict`]. Comments and
+//! blank lines are merged as a whole: the merged text keeps the layout of
+//! "ours", or of "theirs" if only they changed it, and keys only the other
+//! side has are appended in its order. If both sides changed comments
+//! differently, the lines "theirs" added follow between conflict markers.
+//! Lines end like those of "ours".
+//!
+//! Conflicting keys are written between git-style conflict markers. The
+//! markers do not parse as entries, so [`LinoEnv::read`](crate::LinoEnv::read)
+//! skips them but still loads both sides of a conflict, with theirs
+//! winning: resolve every conflict before using the file.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::{parse_str, Event, ParseErrorKind, Span};
+
+/// A key changed differently on both sides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The conflicting key.
+    pub key: String,
+    /// Value in the common ancestor, if the key existed there.
+    pub base: Option<String>,
+    /// Our value, or `None` if we deleted the key.
+    pub ours: Option<String>,
+    /// Their value, or `None` if they deleted the key.
+    pub theirs: Option<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.ours, &self.theirs) {
+            (None, _) => write!(f, "{}: deleted in ours, changed in theirs", self.key),
+            (_, None) => write!(f, "{}: changed in ours, deleted in theirs", self.key),
+            _ if self.base.is_none() => write!(f, "{}: added differently on both sides", self.key),
+            _ => write!(f, "{}: changed differently on both sides", self.key),
+        }
+    }
+}
+
+/// Result of [`merge3`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Merged file content, with conflict markers around conflicting keys.
+    pub text: String,
+    /// Keys that could not be merged automatically.
+    pub conflicts: Vec<Conflict>,
+    /// `true` if both sides changed comments or blank lines differently. The
+    /// text keeps ours and ends with the lines theirs added between conflict
+    /// markers.
+    pub layout_conflict: bool,
+}
+
+impl MergeResult {
+    /// `true` if every key and the comments merged without conflict.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty() && !self.layout_conflict
+    }
+}
+
+/// Effective value and source text of each key (last occurrence wins).
+struct Side<'a> {
+    source: &'a str,
+    events: Vec<Event>,
+    entries: HashMap<String, (String, Span)>,
+}
+
+impl<'a> Side<'a> {
+    fn new(source: &'a str) -> Self {
+        let events = parse_str(source);
+        let mut entries = HashMap::new();
+        for event in &events {
+            if let Event::Entry { key, value, span } = event {
+                entries.insert(key.clone(), (value.clone(), *span));
+            }
+        }
+        Self {
+            source,
+            events,
+            entries,
+        }
+    }
+
+    fn value(&self, key: &str) -> Option<&String> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    fn is_last(&self, key: &str, span: Span) -> bool {
+        self.entries.get(key).is_some_and(|(_, last)| *last == span)
+    }
+
+    fn raw(&self, span: Span) -> &'a str {
+        &self.source[span.start..span.end]
+    }
+
+    fn raw_entry(&self, key: &str) -> Option<&'a str> {
+        self.entries.get(key).map(|(_, span)| self.raw(*span))
+    }
+
+    /// Comments, blank lines and unparsable lines, in order.
+    fn layout(&self) -> Vec<&'a str> {
+        self.events
+            .iter()
+            .filter(|event| match event {
+                Event::Entry { .. } => false,
+                // The entry that follows has the same span and text.
+                Event::Error { kind, .. } => *kind != ParseErrorKind::UnterminatedQuote,
+                _ => true,
+            })
+            .map(|event| self.raw(event.span()))
+            .collect()
+    }
+}
+
+enum Resolution {
+    Ours,
+    Theirs,
+    Conflict(Conflict),
+}
+
+fn resolve(
+    key: &str,
+    base: Option<&String>,
+    ours: Option<&String>,
+    theirs: Option<&String>,
+) -> Resolution {
+    if ours == theirs || theirs == base {
+        Resolution::Ours
+    } else if ours == base {
+        Resolution::Theirs
+    } else {
+        Resolution::Conflict(Conflict {
+            key: key.to_string(),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        })
+    }
+}
+
+/// Merged text, with the line terminator of the input.
+struct Output {
+    text: String,
+    newline: &'static str,
+    conflicts: Vec<Conflict>,
+}
+
+impl Output {
+    fn line(&mut self, line: &str) {
+        self.text.push_str(line);
+        self.text.push_str(self.newline);
+    }
+
+    fn conflict(&mut self, ours: &[&str], theirs: &[&str]) {
+        self.line("<<<<<<< ours");
+        for line in ours {
+            self.line(line);
+        }
+        self.line("=======");
+        for line in theirs {
+            self.line(line);
+        }
+        self.line(">>>>>>> theirs");
+    }
+}
+
+/// The three inputs of a merge.
+struct Sides<'a> {
+    base: Side<'a>,
+    ours: Side<'a>,
+    theirs: Side<'a>,
+}
+
+impl Sides<'_> {
+    fn resolve(&self, key: &str) -> Resolution {
+        resolve(
+            key,
+            self.base.value(key),
+            self.ours.value(key),
+            self.theirs.value(key),
+        )
+    }
+
+    /// `true` if the merge deletes `key`.
+    fn deletes(&self, key: &str) -> bool {
+        match self.resolve(key) {
+            Resolution::Ours => self.ours.value(key).is_none(),
+            Resolution::Theirs => self.theirs.value(key).is_none(),
+            Resolution::Conflict(_) => false,
+        }
+    }
+
+    /// Write the merged entry of `key`, if the merge keeps it.
+    fn merge_entry(&self, key: &str, output: &mut Output) {
+        let raw = match self.resolve(key) {
+            Resolution::Ours => self.ours.raw_entry(key),
+            Resolution::Theirs => self.theirs.raw_entry(key),
+            Resolution::Conflict(conflict) => {
+                let ours: Vec<&str> = self.ours.raw_entry(key).into_iter().collect();
+                let theirs: Vec<&str> = self.theirs.raw_entry(key).into_iter().collect();
+                output.conflict(&ours, &theirs);
+                output.conflicts.push(conflict);
+                return;
+            }
+        };
+        if let Some(raw) = raw {
+            output.line(raw);
+        }
+    }
+
+    /// Write the lines of `layout` with its entries merged, then the keys
+    /// only `other` has, in its order.
+    fn walk(&self, layout: &Side<'_>, other: &Side<'_>, output: &mut Output) {
+        for event in &layout.events {
+            let (key, span) = match event {
+                Event::Entry { key, span, .. } => (key, *span),
+                Event::Error {
+                    kind: ParseErrorKind::UnterminatedQuote,
+                    ..
+                } => continue,
+                _ => {
+                    output.line(layout.raw(event.span()));
+                    continue;
+                }
+            };
+            // A deleted key loses its shadowed duplicates too, which would
+            // otherwise become the effective value again.
+            if self.deletes(key) {
+                continue;
+            }
+            // Shadowed duplicates are kept verbatim; only the effective entry merges.
+            if layout.is_last(key, span) {
+                self.merge_entry(key, output);
+            } else {
+                output.line(layout.raw(span));
+            }
+        }
+
+        for event in &other.events {
+            if let Event::Entry { key, span, .. } = event {
+                if layout.value(key).is_none() && other.is_last(key, *span) {
+                    self.merge_entry(key, output);
+                }
+            }
+        }
+    }
+}
+
+/// Merge `ours` and `theirs`, both derived from `base`, key by key.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::merge3;
+///
+/// let base = "# Service\nHOST: localhost\nPORT: 80\n";
+/// let ours = "# Service\nHOST: example.com\nPORT: 80\n";
+/// let theirs = "# Service\nHOST: localhost\nPORT: 8080\nDEBUG: true\n";
+///
+/// let merged = merge3(base, ours, theirs);
+/// assert!(merged.is_clean());
+/// assert_eq!(merged.text, "# Service\nHOST: example.com\nPORT: 8080\nDEBUG: true\n");
+/// ```
+#[must_use]
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let sides = Sides {
+        base: Side::new(base),
+        ours: Side::new(ours),
+        theirs: Side::new(theirs),
+    };
+    let crlf = if ours.is_empty() {
+        theirs.contains("\r\n")
+    } else {
+        ours.contains("\r\n")
+    };
+    let mut output = Output {
+        text: String::new(),
+        newline: if crlf { "\r\n" } else { "\n" },
+        conflicts: Vec::new(),
+    };
+
+    let base_layout = sides.base.layout();
+    let ours_layout = sides.ours.layout();
+    let theirs_layout = sides.theirs.layout();
+    let ours_changed = ours_layout != base_layout;
+    let theirs_changed = theirs_layout != base_layout;
+    let layout_conflict = ours_changed && theirs_changed && ours_layout != theirs_layout;
+
+    if theirs_changed && !ours_changed {
+        sides.walk(&sides.theirs, &sides.ours, &mut output);
+    } else {
+        sides.walk(&sides.ours, &sides.theirs, &mut output);
+    }
+    if layout_conflict {
+        let added: Vec<&str> = theirs_layout
+            .into_iter()
+            .filter(|line| !base_layout.contains(line) && !ours_layout.contains(line))
+            .collect();
+        output.conflict(&[], &added);
+    }
+
+    MergeResult {
+        text: output.text,
+        conflicts: output.conflicts,
+        layout_conflict,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_overlapping_changes() {
+        let merged = merge3(
+            "A: 1\nB: 2\nC: 3\n",
+            "A: 10\nB: 2\nC: 3\n",
+            "A: 1\nC: 3\nD: 4\n",
+        );
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "A: 10\nC: 3\nD: 4\n");
+    }
+
+    #[test]
+    fn test_same_change_on_both_sides() {
+        let merged = merge3("A: 1\n", "A: 2\n", "# note\nA: 2\n");
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "# note\nA: 2\n");
+    }
+
+    #[test]
+    fn test_comments_from_theirs() {
+        let merged = merge3(
+            "# old\nA: 1\nB: 2\n",
+            "# old\nA: 10\nC: 3\n",
+            "# new\nA: 1\n\nB: 2\n",
+        );
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "# new\nA: 10\n\nC: 3\n");
+    }
+
+    #[test]
+    fn test_comment_conflict() {
+        let merged = merge3("A: 1\n", "# ours\nA: 1\n", "# theirs\nA: 2\n");
+        assert!(merged.conflicts.is_empty());
+        assert!(merged.layout_conflict);
+        assert!(!merged.is_clean());
+        assert_eq!(
+            merged.text,
+            "# ours\nA: 2\n<<<<<<< ours\n=======\n# theirs\n>>>>>>> theirs\n"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_change() {
+        let merged = merge3("A: 1\nB: 2\n", "A: 2\nB: 2\n", "A: 3\nB: 2\n");
+        assert_eq!(
+            merged.conflicts,
+            vec![Conflict {
+                key: "A".to_string(),
+                base: Some("1".to_string()),
+                ours: Some("2".to_string()),
+                theirs: Some("3".to_string()),
+            }]
+        );
+        assert_eq!(
+            merged.text,
+            "<<<<<<< ours\nA: 2\n=======\nA: 3\n>>>>>>> theirs\nB: 2\n"
+        );
+        assert_eq!(
+            merged.conflicts[0].to_string(),
+            "A: changed differently on both sides"
+        );
+    }
+
+    #[test]
+    fn test_delete_modify_conflict() {
+        let merged = merge3("A: 1\n", "", "A: 2\n");
+        assert_eq!(merged.text, "<<<<<<< ours\n=======\nA: 2\n>>>>>>> theirs\n");
+        assert_eq!(
+            merged.conflicts[0].to_string(),
+            "A: deleted in ours, changed in theirs"
+        );
+
+        let merged = merge3("A: 1\n", "A: 2\n", "");
+        assert_eq!(
+            merged.conflicts[0].to_string(),
+            "A: changed in ours, deleted in theirs"
+        );
+    }
+
+    #[test]
+    fn test_multiline_values_and_duplicates() {
+        let ours = "A: old\nA: \"x\ny\"\nB: 1\n";
+        let theirs = "A: \"x\ny\"\nB: 2\n";
+        let merged = merge3("A: \"x\ny\"\nB: 1\n", ours, theirs);
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "A: old\nA: \"x\ny\"\nB: 2\n");
+    }
+
+    #[test]
+    fn test_deleted_key_drops_duplicates() {
+        let merged = merge3("A: 1\n", "A: old\nA: 1\nB: 2\n", "");
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "B: 2\n");
+    }
+
+    #[test]
+    fn test_unterminated_quote_written_once() {
+        let ours = "A: \"open\nB: 1\n";
+        let merged = merge3(ours, ours, "A: \"open\nB: 2\n");
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "A: \"open\nB: 2\n");
+    }
+
+    #[test]
+    fn test_keeps_crlf() {
+        let merged = merge3("A: 1\r\n", "# note\r\nA: 1\r\n", "A: 2\r\nB: 3\r\n");
+        assert!(merged.is_clean());
+        assert_eq!(merged.text, "# note\r\nA: 2\r\nB: 3\r\n");
+    }
+
+    #[test]
+    fn test_conflicted_file_loads_theirs() {
+        let merged = merge3("A: 1\n", "A: 2\n", "A: 3\n");
+        let mut env = crate::LinoEnv::new("merge_test.lenv");
+        env.read_from(merged.text.as_bytes()).unwrap();
+        assert_eq!(env.get("A").as_deref(), Some("3"));
+    }
+}
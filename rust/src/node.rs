@@ -0,0 +1,384 @@
+//! Nested values as a tree of links.
+//!
+//! A value spanning several lines, written either as an indented block or
+//! as a quoted string, is read as a tree: `key: value` lines become
+//! key/value pairs, `key:` lines with indented children become nested
+//! nodes, and other lines become list items.
+//!
+//! ```text
+//! HIVE_TELEGRAM_BOT_CONFIGURATION:
+//!   TELEGRAM_BOT_TOKEN: 'xxx'
+//!   TELEGRAM_ALLOWED_CHATS:
+//!     -1002975819706
+//!   TELEGRAM_BOT_VERBOSE: true
+//! ```
+//!
+//! Not every tree built by hand has such a text form: a multi-line
+//! [`Node::Value`] inside a map or list, for example, would be read back as
+//! a nested list. [`Node::is_lossless`] tells whether a tree reads back
+//! unchanged, and [`LinoEnv::set_node`] rejects trees that do not.
+
+use std::fmt;
+use std::io;
+
+use crate::parser::indentation;
+use crate::LinoEnv;
+
+/// A value read as a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A single-line value, or a line that is not a `key:` pair.
+    Value(String),
+    /// Ordered `key: value` pairs.
+    Map(Vec<(String, Self)>),
+    /// Items without keys; keyed lines mixed into a list become one-entry maps.
+    List(Vec<Self>),
+}
+
+fn parse_lines(lines: &[&str]) -> Node {
+    let indent = lines
+        .iter()
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    let mut items: Vec<(Option<String>, Node)> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index][indent..].trim_end();
+        let end = lines[index + 1..]
+            .iter()
+            .position(|child| indentation(child) <= indent)
+            .map_or(lines.len(), |position| index + 1 + position);
+        let children = &lines[index + 1..end];
+        index = end;
+
+        if children.is_empty() {
+            if let Some((key, value)) = line.split_once(": ") {
+                items.push((Some(key.trim().to_string()), Node::Value(value.to_string())));
+                continue;
+            }
+        }
+        if let Some(key) = line.strip_suffix(':').filter(|key| !key.trim().is_empty()) {
+            // A `key:` line without children has an empty value.
+            let node = if children.is_empty() {
+                Node::Value(String::new())
+            } else {
+                parse_lines(children)
+            };
+            items.push((Some(key.trim().to_string()), node));
+            continue;
+        }
+        // Anything else is kept verbatim, including its indented lines.
+        let text = children.iter().fold(line.to_string(), |mut text, child| {
+            text.push('\n');
+            text.push_str(&child[indent..]);
+            text
+        });
+        items.push((None, Node::Value(text)));
+    }
+
+    if items.iter().all(|(key, _)| key.is_some()) {
+        Node::Map(
+            items
+                .into_iter()
+                .filter_map(|(key, node)| Some((key?, node)))
+                .collect(),
+        )
+    } else {
+        Node::List(
+            items
+                .into_iter()
+                .map(|(key, node)| match key {
+                    Some(key) => Node::Map(vec![(key, node)]),
+                    None => node,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Node {
+    /// Read a value as a tree.
+    ///
+    /// Single-line values are always [`Node::Value`]; blank lines are
+    /// ignored in multi-line values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::node::Node;
+    ///
+    /// let node = Node::parse("TOKEN: 'xxx'\nCHATS:\n  -100\n  -200\nVERBOSE: true");
+    /// assert_eq!(node.get("VERBOSE").and_then(Node::as_str), Some("true"));
+    /// assert_eq!(node.get("CHATS").and_then(Node::items).map(<[Node]>::len), Some(2));
+    /// ```
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        if !value.contains('\n') {
+            return Self::Value(value.to_string());
+        }
+        let lines: Vec<&str> = value
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        parse_lines(&lines)
+    }
+
+    /// The text of a [`Node::Value`].
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The child of a [`Node::Map`] with the given key (last one wins).
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Map(entries) => entries
+                .iter()
+                .rev()
+                .find(|(name, _)| name == key)
+                .map(|(_, node)| node),
+            _ => None,
+        }
+    }
+
+    /// The items of a [`Node::List`].
+    #[must_use]
+    pub fn items(&self) -> Option<&[Self]> {
+        match self {
+            Self::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Whether the text written by [`Display`](fmt::Display) is read back
+    /// by [`Node::parse`] as this same tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::node::Node;
+    ///
+    /// let chats = Node::List(vec![Node::Value("-100".to_string())]);
+    /// assert!(Node::Map(vec![("CHATS".to_string(), chats)]).is_lossless());
+    ///
+    /// let lines = Node::Value("first\nsecond".to_string());
+    /// assert!(!Node::Map(vec![("TEXT".to_string(), lines)]).is_lossless());
+    /// ```
+    #[must_use]
+    pub fn is_lossless(&self) -> bool {
+        Self::parse(&self.to_string()) == *self
+    }
+
+    fn write_lines(&self, text: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent);
+        match self {
+            Self::Value(value) => {
+                for line in value.lines() {
+                    text.push_str(&pad);
+                    text.push_str(line);
+                    text.push('\n');
+                }
+            }
+            Self::Map(entries) => {
+                for (key, node) in entries {
+                    text.push_str(&pad);
+                    text.push_str(key);
+                    match node {
+                        Self::Value(value) if value.is_empty() => text.push_str(":\n"),
+                        Self::Value(value) if !value.contains('\n') => {
+                            text.push_str(": ");
+                            text.push_str(value);
+                            text.push('\n');
+                        }
+                        _ => {
+                            text.push_str(":\n");
+                            node.write_lines(text, indent + 1);
+                        }
+                    }
+                }
+            }
+            Self::List(items) => {
+                for item in items {
+                    item.write_lines(text, indent);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Node {
+    /// Writes the node as value text that [`Node::parse`] reads back, if
+    /// the node [is lossless](Node::is_lossless).
+    ///
+    /// A map or list that fits on one line, such as a map with one entry,
+    /// starts with a line break so that it is not read back as a
+    /// [`Node::Value`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Self::Value(value) = self {
+            return write!(f, "{value}");
+        }
+        let mut text = String::new();
+        self.write_lines(&mut text, 0);
+        let text = text.trim_end_matches('\n');
+        if text.contains('\n') {
+            write!(f, "{text}")
+        } else {
+            write!(f, "\n{text}")
+        }
+    }
+}
+
+impl LinoEnv {
+    /// Get the value of a key as a tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set("BOT", "TOKEN: xxx\nCHATS:\n  -100");
+    /// let bot = env.get_node("BOT").unwrap();
+    /// assert_eq!(bot.get("TOKEN").and_then(|node| node.as_str()), Some("xxx"));
+    /// ```
+    #[must_use]
+    pub fn get_node(&self, key: &str) -> Option<Node> {
+        self.get(key).map(|value| Node::parse(&value))
+    }
+
+    /// Set a key to a tree, written as an indented block.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::InvalidData`] error, leaving the key
+    /// unchanged, if the tree would not read back unchanged (see
+    /// [`Node::is_lossless`]) or the key is rejected by the
+    /// [`KeyPolicy`](crate::policy::KeyPolicy).
+    pub fn set_node(&mut self, key: &str, node: &Node) -> io::Result<&mut Self> {
+        if !node.is_lossless() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the value of {key:?} cannot be written as a tree that reads back unchanged"
+                ),
+            ));
+        }
+        Ok(self.try_set(key, &node.to_string())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::format_entry;
+
+    fn value(text: &str) -> Node {
+        Node::Value(text.to_string())
+    }
+
+    fn hive() -> Node {
+        Node::Map(vec![
+            ("TELEGRAM_BOT_TOKEN".to_string(), value("'xxx'")),
+            (
+                "TELEGRAM_ALLOWED_CHATS".to_string(),
+                Node::List(vec![value("-1002975819706")]),
+            ),
+            ("TELEGRAM_BOT_VERBOSE".to_string(), value("true")),
+        ])
+    }
+
+    #[test]
+    fn test_quoted_and_indented_forms() {
+        let quoted = "\nTELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true\n";
+        assert_eq!(Node::parse(quoted), hive());
+
+        let tabs = "TELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n\t-1002975819706\nTELEGRAM_BOT_VERBOSE: true";
+        assert_eq!(Node::parse(tabs), hive());
+    }
+
+    #[test]
+    fn test_mixed_list() {
+        let node = Node::parse("plain\nKEY: 1\nplain again");
+        assert_eq!(
+            node,
+            Node::List(vec![
+                value("plain"),
+                Node::Map(vec![("KEY".to_string(), value("1"))]),
+                value("plain again"),
+            ])
+        );
+        assert_eq!(Node::parse(&node.to_string()), node);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let text = hive().to_string();
+        assert_eq!(
+            text,
+            "TELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true"
+        );
+        assert_eq!(Node::parse(&text), hive());
+    }
+
+    #[test]
+    fn test_one_entry_map_round_trip() {
+        let node = Node::Map(vec![("A".to_string(), value("1"))]);
+        assert_eq!(Node::parse(&node.to_string()), node);
+
+        let file = crate::testing::TempLenv::new();
+        let mut env = LinoEnv::new(file.path());
+        env.set_node("ONE", &node).unwrap().write().unwrap();
+        assert_eq!(file.read().unwrap().get_node("ONE"), Some(node));
+    }
+
+    #[test]
+    fn test_empty_value_round_trip() {
+        let node = Node::Map(vec![
+            ("A".to_string(), value("")),
+            ("B".to_string(), value("1")),
+        ]);
+        assert_eq!(node.to_string(), "A:\nB: 1");
+        assert_eq!(Node::parse(&node.to_string()), node);
+
+        let only = Node::Map(vec![("A".to_string(), value(""))]);
+        assert_eq!(Node::parse(&only.to_string()), only);
+    }
+
+    #[test]
+    fn test_set_node_writes_block() {
+        let mut env = LinoEnv::new("node_test.lenv");
+        env.set_node("HIVE_TELEGRAM_BOT_CONFIGURATION", &hive())
+            .unwrap();
+        let value = env.get("HIVE_TELEGRAM_BOT_CONFIGURATION").unwrap();
+        assert_eq!(
+            format_entry("HIVE_TELEGRAM_BOT_CONFIGURATION", &value),
+            "HIVE_TELEGRAM_BOT_CONFIGURATION:\n  TELEGRAM_BOT_TOKEN: 'xxx'\n  TELEGRAM_ALLOWED_CHATS:\n    -1002975819706\n  TELEGRAM_BOT_VERBOSE: true\n"
+        );
+        assert_eq!(
+            env.get_node("HIVE_TELEGRAM_BOT_CONFIGURATION"),
+            Some(hive())
+        );
+    }
+
+    #[test]
+    fn test_rejects_trees_that_do_not_read_back() {
+        let lines = value("first\nsecond");
+        for node in [
+            Node::Map(vec![("TEXT".to_string(), lines.clone())]),
+            Node::List(vec![lines, value("third")]),
+            Node::List(vec![value("A: 1")]),
+        ] {
+            assert!(!node.is_lossless(), "{node:?}");
+            let mut env = LinoEnv::new("node_test.lenv");
+            let error = env.set_node("TREE", &node).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(!env.has("TREE"));
+        }
+        assert!(hive().is_lossless());
+    }
+}
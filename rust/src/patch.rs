@@ -0,0 +1,529 @@
+//! Changes to an environment expressed as data.
+//!
+//! An [`EnvPatch`] is a list of operations written one per line in links
+//! notation:
+//!
+//! ```text
+//! set HOST example.com
+//! set GREETING "hello world"
+//! delete DEBUG
+//! rename DB_URL DATABASE_URL
+//! ```
+//!
+//! References containing whitespace, quotes or parentheses are quoted with
+//! `"`, `'` or `` ` ``, and a quoted reference may span several lines. A
+//! reference containing all three quotes is quoted with `"`, written twice
+//! for each `"` inside it.
+//! Applying a patch is idempotent: applying it a second time changes nothing.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use zeroize::Zeroize;
+
+use crate::diff::{Change, EnvDiff};
+use crate::policy::KeyError;
+use crate::LinoEnv;
+
+/// A single patch operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    /// Set `key` to `value`.
+    Set { key: String, value: String },
+    /// Remove `key` if it exists.
+    Delete { key: String },
+    /// Move the value of `from` to `to`, replacing any value of `to`.
+    Rename { from: String, to: String },
+}
+
+/// Quote `reference` for links notation if it is not a plain word.
+fn quote(reference: &str) -> String {
+    let plain = !reference.is_empty()
+        && !reference
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '(' | ')' | ':'));
+    if plain {
+        return reference.to_string();
+    }
+    ['"', '\'', '`']
+        .into_iter()
+        .find(|quote| !reference.contains(*quote))
+        .map_or_else(
+            || format!("\"{}\"", reference.replace('"', "\"\"")),
+            |quote| format!("{quote}{reference}{quote}"),
+        )
+}
+
+impl fmt::Display for PatchOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set { key, value } => write!(f, "set {} {}", quote(key), quote(value)),
+            Self::Delete { key } => write!(f, "delete {}", quote(key)),
+            Self::Rename { from, to } => write!(f, "rename {} {}", quote(from), quote(to)),
+        }
+    }
+}
+
+/// Error returned when reading an [`EnvPatch`].
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch file could not be read.
+    Io(io::Error),
+    /// The patch text is malformed.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for PatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for PatchError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Split `source` into links: lists of references with their starting line.
+fn links(source: &str) -> Result<Vec<(usize, Vec<String>)>, PatchError> {
+    let mut links = Vec::new();
+    let mut references = Vec::new();
+    let mut line = 1;
+    let mut link_line = 1;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                if !references.is_empty() {
+                    links.push((link_line, std::mem::take(&mut references)));
+                }
+                line += 1;
+            }
+            '(' | ')' => {}
+            c if c.is_whitespace() => {}
+            '"' | '\'' | '`' => {
+                let start = line;
+                let mut reference = String::new();
+                loop {
+                    match chars.next() {
+                        // A doubled quote stands for the quote itself.
+                        Some(next) if next == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            reference.push(c);
+                        }
+                        Some(next) if next == c => break,
+                        Some(next) => {
+                            if next == '\n' {
+                                line += 1;
+                            }
+                            reference.push(next);
+                        }
+                        None => {
+                            return Err(PatchError::Parse {
+                                line: start,
+                                message: format!("unterminated {c} quote"),
+                            })
+                        }
+                    }
+                }
+                if references.is_empty() {
+                    link_line = start;
+                }
+                references.push(reference);
+            }
+            c => {
+                let mut reference = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '(' | ')') {
+                        break;
+                    }
+                    reference.push(next);
+                    chars.next();
+                }
+                if references.is_empty() {
+                    link_line = line;
+                }
+                references.push(reference);
+            }
+        }
+    }
+    if !references.is_empty() {
+        links.push((link_line, references));
+    }
+    Ok(links)
+}
+
+/// An ordered list of [`PatchOp`]s.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvPatch {
+    /// Operations, applied in order.
+    pub ops: Vec<PatchOp>,
+}
+
+impl EnvPatch {
+    /// Create an empty patch.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a `set` operation.
+    pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+        self.ops.push(PatchOp::Set {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Add a `delete` operation.
+    pub fn delete(&mut self, key: &str) -> &mut Self {
+        self.ops.push(PatchOp::Delete {
+            key: key.to_string(),
+        });
+        self
+    }
+
+    /// Add a `rename` operation.
+    pub fn rename(&mut self, from: &str, to: &str) -> &mut Self {
+        self.ops.push(PatchOp::Rename {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        self
+    }
+
+    /// Parse a patch from links notation.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PatchError::Parse`] with the line of the first malformed
+    /// operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::patch::{EnvPatch, PatchOp};
+    ///
+    /// let patch = EnvPatch::parse("set GREETING 'hello world'\ndelete DEBUG\n").unwrap();
+    /// assert_eq!(patch.ops[1], PatchOp::Delete { key: "DEBUG".to_string() });
+    /// assert_eq!(patch.to_string(), "set GREETING \"hello world\"\ndelete DEBUG\n");
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, PatchError> {
+        let mut patch = Self::new();
+        for (line, references) in links(source)? {
+            let error = |message: String| PatchError::Parse { line, message };
+            match references
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .as_slice()
+            {
+                ["set", key, value] => patch.set(key, value),
+                ["delete", key] => patch.delete(key),
+                ["rename", from, to] => patch.rename(from, to),
+                ["set" | "delete" | "rename", ..] => {
+                    return Err(error(format!(
+                        "wrong number of arguments to `{}`",
+                        references[0]
+                    )))
+                }
+                [operation, ..] => return Err(error(format!("unknown operation `{operation}`"))),
+                [] => continue,
+            };
+        }
+        Ok(patch)
+    }
+
+    /// Read a patch file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, PatchError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+}
+
+impl fmt::Display for EnvPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            writeln!(f, "{op}")?;
+        }
+        Ok(())
+    }
+}
+
+impl EnvPatch {
+    /// Patch turning `old` into `new`.
+    ///
+    /// Values are taken as `new` stores them, so encrypted values stay
+    /// encrypted, while keys are compared by their effective values as in
+    /// [`LinoEnv::diff`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::patch::EnvPatch;
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut old = LinoEnv::new("old.lenv");
+    /// old.set("A", "1").set("B", "2");
+    /// let mut new = LinoEnv::new("new.lenv");
+    /// new.set("A", "10");
+    /// assert_eq!(EnvPatch::between(&old, &new).to_string(), "set A 10\ndelete B\n");
+    /// ```
+    #[must_use]
+    pub fn between(old: &LinoEnv, new: &LinoEnv) -> Self {
+        let mut patch = Self::new();
+        for change in &old.diff(new).changes {
+            match change {
+                Change::Added { key, .. } | Change::Changed { key, .. } => {
+                    let stored = &new.data[new.resolve_key(key).as_ref()];
+                    patch.set(key, stored)
+                }
+                Change::Removed { key, .. } => patch.delete(key),
+            };
+        }
+        patch
+    }
+}
+
+impl From<&EnvDiff> for EnvPatch {
+    /// Patch turning the first environment of the diff into the second.
+    ///
+    /// Diffs hold decrypted values; use [`EnvPatch::between`] to keep
+    /// encrypted values encrypted.
+    fn from(diff: &EnvDiff) -> Self {
+        let mut patch = Self::new();
+        for change in &diff.changes {
+            match change {
+                Change::Added { key, value }
+                | Change::Changed {
+                    key, new: value, ..
+                } => patch.set(key, value),
+                Change::Removed { key, .. } => patch.delete(key),
+            };
+        }
+        patch
+    }
+}
+
+impl LinoEnv {
+    /// Apply `patch` in memory and return what changed.
+    ///
+    /// Deleting a missing key and renaming a missing key are no-ops, so
+    /// applying the same patch twice yields an empty diff the second time.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KeyError`] and leaves the environment unchanged if a key
+    /// set or renamed to is rejected by the
+    /// [`KeyPolicy`](crate::policy::KeyPolicy).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::patch::EnvPatch;
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new("app.lenv");
+    /// env.set("DB_URL", "postgres://localhost").set("DEBUG", "true");
+    ///
+    /// let mut patch = EnvPatch::new();
+    /// patch.rename("DB_URL", "DATABASE_URL").delete("DEBUG");
+    ///
+    /// assert_eq!(env.apply_patch(&patch).unwrap().changes.len(), 3);
+    /// assert_eq!(env.get("DATABASE_URL"), Some("postgres://localhost".to_string()));
+    /// assert!(env.apply_patch(&patch).unwrap().is_empty());
+    /// ```
+    pub fn apply_patch(&mut self, patch: &EnvPatch) -> Result<EnvDiff, KeyError> {
+        let before = self.clone();
+        if let Err(error) = self.apply_ops(&patch.ops) {
+            *self = before;
+            return Err(error);
+        }
+        Ok(before.diff(self))
+    }
+
+    fn apply_ops(&mut self, ops: &[PatchOp]) -> Result<(), KeyError> {
+        for op in ops {
+            match op {
+                PatchOp::Set { key, value } => {
+                    self.try_set(key, value)?;
+                }
+                PatchOp::Delete { key } => {
+                    self.delete(key);
+                }
+                PatchOp::Rename { from, to } => {
+                    let from = self.resolve_key(from).into_owned();
+                    let to = self.resolve_key(to).into_owned();
+                    if from == to || !self.data.contains_key(&from) {
+                        continue;
+                    }
+                    self.key_policy.check(&to)?;
+                    if let Some(mut value) = self.data.remove(&from) {
+                        self.delete(&to);
+                        self.layout.rename(&from, &to);
+                        self.set(&to, &value);
+                        value.zeroize();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return what [`LinoEnv::apply_patch`] would change, without changing
+    /// anything.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KeyError`] if applying the patch would.
+    pub fn preview_patch(&self, patch: &EnvPatch) -> Result<EnvDiff, KeyError> {
+        self.clone().apply_patch(patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::KeyPolicy;
+
+    #[test]
+    fn test_round_trip() {
+        let mut patch = EnvPatch::new();
+        patch
+            .set("A", "plain")
+            .set("B", "two words")
+            .set("C", "say \"hi\"")
+            .set("D", "line 1\nline 2")
+            .set("E", "")
+            .set("F", "all \"quotes' `here`\"")
+            .set("G", "\"'`")
+            .delete("F")
+            .rename("G", "H");
+        assert_eq!(EnvPatch::parse(&patch.to_string()).unwrap(), patch);
+    }
+
+    #[test]
+    fn test_parse_links() {
+        let patch = EnvPatch::parse("(set A 1)\n\n(delete B)\n").unwrap();
+        assert_eq!(patch.ops.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = EnvPatch::parse("set A 1\nremove B\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown operation `remove`");
+
+        let error = EnvPatch::parse("set A\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: wrong number of arguments to `set`"
+        );
+
+        let error = EnvPatch::parse("set A\n'oops\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unterminated ' quote");
+    }
+
+    #[test]
+    fn test_patch_from_diff() {
+        let mut old = LinoEnv::new("old.lenv");
+        old.set("A", "1").set("B", "2");
+        let mut new = LinoEnv::new("new.lenv");
+        new.set("A", "10").set("C", "3");
+
+        let patch = EnvPatch::from(&old.diff(&new));
+        assert_eq!(patch.to_string(), "set A 10\ndelete B\nset C 3\n");
+        assert_eq!(EnvPatch::between(&old, &new), patch);
+        old.apply_patch(&patch).unwrap();
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_patch_keeps_encrypted_values() {
+        use crate::encryption::{encrypt_value, generate_keypair, PRIVATE_KEY_NAME};
+
+        let mut guard = crate::testing::EnvGuard::new();
+        let (public_key, private_key) = generate_keypair();
+        guard.set(PRIVATE_KEY_NAME, &private_key);
+        let encrypted = encrypt_value(&public_key, "secret").unwrap();
+        let old = LinoEnv::new("old.lenv");
+        let mut new = LinoEnv::new("new.lenv");
+        new.set("TOKEN", &encrypted);
+
+        assert_eq!(new.get("TOKEN"), Some("secret".to_string()));
+        let patch = EnvPatch::between(&old, &new);
+        assert_eq!(
+            patch.ops,
+            vec![PatchOp::Set {
+                key: "TOKEN".to_string(),
+                value: encrypted
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rename_follows_key_policy() {
+        let mut env = LinoEnv::new("rename_policy.lenv");
+        env.set_key_policy(KeyPolicy {
+            uppercase: true,
+            ..KeyPolicy::default()
+        });
+        env.set("DB_URL", "postgres://localhost");
+        let mut patch = EnvPatch::new();
+        patch.rename("db_url", "database_url");
+        env.apply_patch(&patch).unwrap();
+        assert_eq!(env.keys(), vec!["DATABASE_URL"]);
+
+        env.set_key_policy(KeyPolicy {
+            posix_names: true,
+            ..KeyPolicy::default()
+        });
+        let mut patch = EnvPatch::new();
+        patch
+            .set("PORT", "5432")
+            .rename("DATABASE_URL", "database url");
+        let error = env.apply_patch(&patch).unwrap_err();
+        assert_eq!(error.key, "database url");
+        assert_eq!(env.keys(), vec!["DATABASE_URL"]);
+
+        let mut patch = EnvPatch::new();
+        patch.set("bad key", "1");
+        assert!(env.preview_patch(&patch).is_err());
+        assert!(!env.has("bad key"));
+    }
+
+    #[test]
+    fn test_preview_does_not_modify() {
+        let mut env = LinoEnv::new("preview.lenv");
+        env.set("A", "1");
+        let mut patch = EnvPatch::new();
+        patch.set("A", "2");
+
+        assert_eq!(
+            env.preview_patch(&patch).unwrap().to_string(),
+            "~ A: 1 -> 2\n"
+        );
+        assert_eq!(env.get("A"), Some("1".to_string()));
+    }
+}
@@ -0,0 +1,348 @@
+//! File permission and exposure checks for `.lenv` files.
+//!
+//! [`LinoEnv::write`] creates new files readable only by their owner
+//! ([`DEFAULT_FILE_MODE`]), and [`LinoEnv::audit_permissions`] reports
+//! existing files that other users could read or that git could commit.
+//!
+//! Whether git ignores a file is asked from `git check-ignore`, which knows
+//! every rule git applies. When git is not installed or does not recognize
+//! the repository, the `.gitignore` files between the file and the
+//! repository root are read instead, supporting `*`, a leading or trailing
+//! `/`, a leading `**/` and `!`. Other syntax (`?`, `[...]`, `\` escapes,
+//! `**` elsewhere in a pattern), `.git/info/exclude` and the global excludes
+//! file are not understood there.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::glob::glob_match;
+use crate::LinoEnv;
+
+/// Mode used for files created by [`LinoEnv::write`] (owner read/write).
+pub const DEFAULT_FILE_MODE: u32 = 0o600;
+
+/// A problem found by [`LinoEnv::audit_permissions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionWarning {
+    /// The file can be read or written by its group or by other users.
+    GroupOrWorldAccessible { mode: u32 },
+    /// The file is owned by a different user than the current one.
+    OwnedByOtherUser { owner: u32, user: u32 },
+    /// The file is inside a git repository but not ignored by git.
+    NotGitignored { repository: PathBuf },
+}
+
+impl fmt::Display for PermissionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GroupOrWorldAccessible { mode } => write!(
+                f,
+                "accessible by group or other users (mode {mode:03o}); run `chmod 600`"
+            ),
+            Self::OwnedByOtherUser { owner, user } => {
+                write!(f, "owned by uid {owner}, not the current user (uid {user})")
+            }
+            Self::NotGitignored { repository } => write!(
+                f,
+                "not ignored by git in the repository at {}",
+                repository.display()
+            ),
+        }
+    }
+}
+
+fn find_repository(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Whether a single `.gitignore` pattern matches `relative`, a `/`-separated
+/// path relative to the directory containing the `.gitignore`.
+fn gitignore_pattern_matches(pattern: &str, relative: &str) -> bool {
+    let (pattern, directory_only) = pattern
+        .strip_suffix('/')
+        .map_or((pattern, false), |pattern| (pattern, true));
+    let pattern = pattern.strip_prefix("**/").unwrap_or(pattern);
+
+    let components: Vec<&str> = relative.split('/').collect();
+    // A matching directory ignores everything below it.
+    let candidates = if directory_only {
+        components.len() - 1
+    } else {
+        components.len()
+    };
+
+    // Patterns containing a `/` are relative to the `.gitignore` directory;
+    // others match a file or directory name at any depth.
+    let anchored = pattern
+        .strip_prefix('/')
+        .or_else(|| pattern.contains('/').then_some(pattern));
+    anchored.map_or_else(
+        || {
+            components[..candidates]
+                .iter()
+                .any(|component| glob_match(pattern, component))
+        },
+        |anchored| {
+            (1..=candidates).any(|count| glob_match(anchored, &components[..count].join("/")))
+        },
+    )
+}
+
+/// Ask `git check-ignore` whether `file` is ignored, or `None` if git is not
+/// installed or does not recognize the repository.
+fn git_check_ignore(file: &Path) -> Option<bool> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(file.parent()?)
+        .args(["check-ignore", "--quiet", "--"])
+        .arg(file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    // 0: ignored, 1: not ignored, anything else: an error.
+    match status.code() {
+        Some(0) => Some(true),
+        Some(1) => Some(false),
+        _ => None,
+    }
+}
+
+/// Check whether `file` is ignored by the `.gitignore` files between it and
+/// `repository`. Later and deeper patterns win, and `!` negates.
+fn is_gitignored(file: &Path, repository: &Path) -> bool {
+    let mut directories: Vec<&Path> = file
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(repository))
+        .collect();
+    directories.reverse();
+
+    let mut ignored = false;
+    for directory in directories {
+        let Ok(content) = fs::read_to_string(directory.join(".gitignore")) else {
+            continue;
+        };
+        let Ok(relative) = file.strip_prefix(directory) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (pattern, negated) = line
+                .strip_prefix('!')
+                .map_or((line, false), |pattern| (pattern, true));
+            if gitignore_pattern_matches(pattern, &relative) {
+                ignored = !negated;
+            }
+        }
+    }
+    ignored
+}
+
+/// The effective user ID of the process: the owner of a file it creates,
+/// since the standard library has no safe `geteuid`.
+#[cfg(unix)]
+fn current_uid() -> io::Result<u32> {
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::OnceLock;
+
+    static UID: OnceLock<u32> = OnceLock::new();
+    if let Some(uid) = UID.get() {
+        return Ok(*uid);
+    }
+    let probe = std::env::temp_dir().join(format!(".lino_env_uid_{}", std::process::id()));
+    let metadata = fs::File::create(&probe).and_then(|file| file.metadata());
+    fs::remove_file(&probe).ok();
+    let uid = metadata?.uid();
+    Ok(*UID.get_or_init(|| uid))
+}
+
+impl LinoEnv {
+    /// Set the mode used when [`LinoEnv::write`] creates a new file.
+    ///
+    /// Existing files keep their permissions. Only has an effect on Unix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    /// let mut env = LinoEnv::new(".lenv.example");
+    /// env.set_file_mode(0o644);
+    /// assert_eq!(env.file_mode(), 0o644);
+    /// ```
+    pub fn set_file_mode(&mut self, mode: u32) -> &mut Self {
+        self.file_mode = mode;
+        self
+    }
+
+    /// The mode used when [`LinoEnv::write`] creates a new file.
+    #[must_use]
+    pub const fn file_mode(&self) -> u32 {
+        self.file_mode
+    }
+
+    /// Check the file for exposure risks.
+    ///
+    /// Reports a file that is accessible by group or other users or owned by
+    /// another user (Unix only), and a file inside a git repository that git
+    /// does not ignore (see the [module documentation](self) for how that is
+    /// decided without git). A missing file yields no warnings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file's metadata cannot be read.
+    pub fn audit_permissions(&self) -> io::Result<Vec<PermissionWarning>> {
+        let path = Path::new(&self.file_path);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut warnings = Vec::new();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+
+            let metadata = fs::metadata(path)?;
+            let mode = metadata.mode() & 0o777;
+            if mode & 0o077 != 0 {
+                warnings.push(PermissionWarning::GroupOrWorldAccessible { mode });
+            }
+            let user = current_uid()?;
+            if metadata.uid() != user {
+                warnings.push(PermissionWarning::OwnedByOtherUser {
+                    owner: metadata.uid(),
+                    user,
+                });
+            }
+        }
+
+        let file = fs::canonicalize(path)?;
+        if let Some(repository) = file.parent().and_then(find_repository) {
+            let ignored =
+                git_check_ignore(&file).unwrap_or_else(|| is_gitignored(&file, &repository));
+            if !ignored {
+                warnings.push(PermissionWarning::NotGitignored { repository });
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, TempLenv};
+
+    #[test]
+    fn test_gitignore_patterns() {
+        assert!(gitignore_pattern_matches(".lenv", ".lenv"));
+        assert!(gitignore_pattern_matches(".lenv", "config/.lenv"));
+        assert!(gitignore_pattern_matches("*.lenv", "config/prod.lenv"));
+        assert!(gitignore_pattern_matches(
+            "/config/*.lenv",
+            "config/prod.lenv"
+        ));
+        assert!(gitignore_pattern_matches("config/", "config/prod.lenv"));
+        assert!(gitignore_pattern_matches("**/.lenv", "a/b/.lenv"));
+        assert!(!gitignore_pattern_matches("/.lenv", "config/.lenv"));
+        assert!(!gitignore_pattern_matches(".lenv/", ".lenv"));
+        assert!(!gitignore_pattern_matches(".env", ".lenv"));
+    }
+
+    #[test]
+    fn test_audit_repository() {
+        let root = TempDir::new().unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("config")).unwrap();
+        let file = root.join("config").join(".lenv");
+        let path = file.to_str().unwrap();
+
+        let mut env = LinoEnv::new(path);
+        env.set("KEY", "value");
+        env.write().unwrap();
+
+        let warnings = env.audit_permissions().unwrap();
+        assert!(matches!(
+            warnings.as_slice(),
+            [PermissionWarning::NotGitignored { .. }]
+        ));
+
+        fs::write(root.join(".gitignore"), "*.lenv\n.lenv\n").unwrap();
+        assert!(env.audit_permissions().unwrap().is_empty());
+
+        fs::write(root.join("config").join(".gitignore"), "!.lenv\n").unwrap();
+        assert_eq!(env.audit_permissions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_audit_asks_git() {
+        let root = TempDir::new().unwrap();
+        let initialized = Command::new("git")
+            .arg("-C")
+            .arg(root.path())
+            .args(["init", "--quiet"])
+            .status();
+        if !initialized.is_ok_and(|status| status.success()) {
+            // git is not installed; `test_audit_repository` covers the fallback.
+            return;
+        }
+        let file = root.join(".lenv");
+        let mut env = LinoEnv::new(file.to_str().unwrap());
+        env.set("KEY", "value");
+        env.write().unwrap();
+
+        // Bracket expressions are only understood by git itself.
+        fs::write(root.join(".gitignore"), "[.]lenv\n").unwrap();
+        assert!(!is_gitignored(
+            &fs::canonicalize(&file).unwrap(),
+            root.path()
+        ));
+        assert!(env.audit_permissions().unwrap().is_empty());
+
+        fs::write(root.join(".gitignore"), "").unwrap();
+        assert!(matches!(
+            env.audit_permissions().unwrap().as_slice(),
+            [PermissionWarning::NotGitignored { .. }]
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_creates_private_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let file = TempLenv::new();
+        let path = file.path();
+        let mut env = LinoEnv::new(path);
+        env.set("KEY", "value");
+        env.write().unwrap();
+
+        let mode = fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert!(env.audit_permissions().unwrap().is_empty());
+
+        fs::set_permissions(path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            env.audit_permissions().unwrap(),
+            vec![PermissionWarning::GroupOrWorldAccessible { mode: 0o644 }]
+        );
+    }
+}
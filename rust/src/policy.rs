@@ -0,0 +1,383 @@
+//! Rules for key names.
+//!
+//! By default any text before `: ` is a key. A [`KeyPolicy`] set with
+//! [`LinoEnv::set_key_policy`] can restrict keys to names other tools accept
+//! as environment variables, normalize them to uppercase, or make lookups
+//! ignore case. The policy is enforced when reading, by [`LinoEnv::set`]
+//! (which panics) and [`LinoEnv::try_set`] (which returns an error), and
+//! before writing; [`KeyPolicy::lint`] reports every offending key of a file
+//! at once. Case is always compared and converted for ASCII letters only.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use zeroize::Zeroize;
+
+use crate::parser::{parse_str, Event};
+use crate::LinoEnv;
+
+/// Check that `key` is a POSIX environment variable name: ASCII letters,
+/// digits and `_`, not starting with a digit.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::policy::is_posix_name;
+///
+/// assert!(is_posix_name("DB_HOST"));
+/// assert!(!is_posix_name("db host"));
+/// assert!(!is_posix_name("1PASSWORD"));
+/// ```
+#[must_use]
+pub fn is_posix_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Which key names are accepted and how they are matched.
+///
+/// The default policy accepts any key and matches it exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyPolicy {
+    /// Reject keys that are not POSIX environment variable names.
+    pub posix_names: bool,
+    /// Convert ASCII letters in keys to uppercase on read, set and lookup.
+    pub uppercase: bool,
+    /// Match keys ignoring ASCII case, keeping the first spelling seen.
+    pub case_insensitive: bool,
+}
+
+/// What is wrong with a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyErrorKind {
+    /// The key is not a POSIX environment variable name.
+    NotPosix,
+    /// The key contains lowercase ASCII letters (reported by
+    /// [`KeyPolicy::lint`]).
+    NotUppercase,
+    /// The key differs from an earlier key only by case (reported by
+    /// [`KeyPolicy::lint`]).
+    CaseConflict {
+        /// The earlier spelling.
+        other: String,
+    },
+}
+
+/// A key rejected by a [`KeyPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyError {
+    /// The offending key.
+    pub key: String,
+    /// What is wrong with it.
+    pub kind: KeyErrorKind,
+    /// 1-based line of the key, when read from a file.
+    pub line: Option<usize>,
+}
+
+impl KeyError {
+    const fn new(key: String, kind: KeyErrorKind) -> Self {
+        Self {
+            key,
+            kind,
+            line: None,
+        }
+    }
+
+    #[must_use]
+    pub(crate) const fn at_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        let key = &self.key;
+        match &self.kind {
+            KeyErrorKind::NotPosix => write!(
+                f,
+                "`{key}` is not a valid environment variable name \
+                 (use letters, digits and `_`, not starting with a digit)"
+            ),
+            KeyErrorKind::NotUppercase => write!(f, "`{key}` is not uppercase"),
+            KeyErrorKind::CaseConflict { other } => {
+                write!(f, "`{key}` differs from `{other}` only by case")
+            }
+        }
+    }
+}
+
+impl Error for KeyError {}
+
+impl From<KeyError> for io::Error {
+    fn from(error: KeyError) -> Self {
+        Self::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+impl KeyPolicy {
+    /// Keys must be POSIX names and are converted to uppercase.
+    #[must_use]
+    pub const fn strict() -> Self {
+        Self {
+            posix_names: true,
+            uppercase: true,
+            case_insensitive: false,
+        }
+    }
+
+    /// Apply uppercase normalization to `key`.
+    #[must_use]
+    pub fn normalize<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if self.uppercase && key.bytes().any(|b| b.is_ascii_lowercase()) {
+            Cow::Owned(key.to_ascii_uppercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+
+    /// Normalize `key` and check that it is accepted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeyErrorKind::NotPosix`] if POSIX names are required and
+    /// the normalized key is not one.
+    pub fn check<'a>(&self, key: &'a str) -> Result<Cow<'a, str>, KeyError> {
+        let key = self.normalize(key);
+        if self.posix_names && !is_posix_name(&key) {
+            return Err(KeyError::new(key.into_owned(), KeyErrorKind::NotPosix));
+        }
+        Ok(key)
+    }
+
+    /// Report every key of `source` that breaks this policy, without
+    /// normalizing anything.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::policy::KeyPolicy;
+    ///
+    /// let errors = KeyPolicy::strict().lint("DB_HOST: a\ndb port: 1\n");
+    /// let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    /// assert_eq!(messages, vec![
+    ///     "line 2: `db port` is not a valid environment variable name (use letters, digits and `_`, not starting with a digit)",
+    ///     "line 2: `db port` is not uppercase",
+    /// ]);
+    /// ```
+    #[must_use]
+    pub fn lint(&self, source: &str) -> Vec<KeyError> {
+        let mut errors = Vec::new();
+        let mut spellings: HashMap<String, String> = HashMap::new();
+        for event in parse_str(source) {
+            let Event::Entry { key, span, .. } = event else {
+                continue;
+            };
+            let line = span.start_line;
+            if self.posix_names && !is_posix_name(&key) {
+                errors.push(KeyError::new(key.clone(), KeyErrorKind::NotPosix).at_line(line));
+            }
+            if self.uppercase && key.bytes().any(|b| b.is_ascii_lowercase()) {
+                errors.push(KeyError::new(key.clone(), KeyErrorKind::NotUppercase).at_line(line));
+            }
+            if self.case_insensitive {
+                let other = spellings
+                    .entry(key.to_ascii_lowercase())
+                    .or_insert_with(|| key.clone());
+                if *other != key {
+                    let kind = KeyErrorKind::CaseConflict {
+                        other: other.clone(),
+                    };
+                    errors.push(KeyError::new(key, kind).at_line(line));
+                }
+            }
+        }
+        errors
+    }
+}
+
+impl LinoEnv {
+    /// Set the rules for keys; they apply to later reads, sets and writes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::policy::KeyPolicy;
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set_key_policy(KeyPolicy::strict());
+    /// env.set("db_host", "localhost");
+    /// assert_eq!(env.keys(), vec!["DB_HOST"]);
+    /// assert_eq!(env.get("db_host"), Some("localhost".to_string()));
+    /// assert!(env.try_set("db host", "x").is_err());
+    /// ```
+    pub fn set_key_policy(&mut self, policy: KeyPolicy) -> &mut Self {
+        self.key_policy = policy;
+        self
+    }
+
+    /// The rules for keys.
+    #[must_use]
+    pub const fn key_policy(&self) -> KeyPolicy {
+        self.key_policy
+    }
+
+    /// Like [`LinoEnv::set`], but return an error instead of panicking for
+    /// keys the policy does not accept.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KeyError`] if the key is rejected.
+    pub fn try_set(&mut self, key: &str, value: &str) -> Result<&mut Self, KeyError> {
+        let key = self.resolve_key(key).into_owned();
+        self.key_policy.check(&key)?;
+        self.layout.add(&key);
+        if let Some(mut old) = self.data.insert(key, value.to_string()) {
+            old.zeroize();
+        }
+        Ok(self)
+    }
+
+    /// The stored spelling of `key` under the current policy. If several
+    /// stored keys match ignoring case, the one that comes first in the file
+    /// wins.
+    pub(crate) fn resolve_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        let key = self.key_policy.normalize(key);
+        if !self.key_policy.case_insensitive || self.data.contains_key(key.as_ref()) {
+            return key;
+        }
+        self.layout
+            .keys()
+            .iter()
+            .find(|existing| existing.eq_ignore_ascii_case(&key))
+            .map_or(key, |existing| Cow::Owned(existing.clone()))
+    }
+
+    /// Check every stored key against the policy.
+    pub(crate) fn check_keys(&self) -> Result<(), KeyError> {
+        for key in self.data.keys() {
+            self.key_policy.check(key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempLenv;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_rejects_invalid_keys() {
+        let mut env = LinoEnv::new("policy_read.lenv");
+        env.set_key_policy(KeyPolicy {
+            posix_names: true,
+            ..KeyPolicy::default()
+        });
+        let error = env.read_from(&b"A: 1\nmy key: 2\n"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .starts_with("line 2: `my key` is not a valid"));
+    }
+
+    #[test]
+    fn test_uppercase_normalization() {
+        let mut env = LinoEnv::new("policy_upper.lenv");
+        env.set_key_policy(KeyPolicy {
+            uppercase: true,
+            ..KeyPolicy::default()
+        });
+        env.read_from(&b"db_host: a\nDB_HOST: b\n"[..]).unwrap();
+        assert_eq!(env.keys(), vec!["DB_HOST"]);
+        assert_eq!(env.get("Db_Host"), Some("b".to_string()));
+        env.delete("db_host");
+        assert!(!env.has("DB_HOST"));
+    }
+
+    #[test]
+    fn test_case_insensitive_lookup() {
+        let mut env = LinoEnv::new("policy_case.lenv");
+        env.set_key_policy(KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        });
+        env.set("Api_Url", "a").set("API_URL", "b");
+        assert_eq!(env.keys(), vec!["Api_Url"]);
+        assert_eq!(env.get("api_url"), Some("b".to_string()));
+    }
+
+    #[test]
+    fn test_case_insensitive_lookup_prefers_first_key() {
+        let mut env = LinoEnv::new("policy_case_order.lenv");
+        env.read_from(
+            &b"b: 0
+API_URL: 1
+api_url: 2
+Api_Url: 3
+"[..],
+        )
+        .unwrap();
+        env.set_key_policy(KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        });
+        for _ in 0..10 {
+            assert_eq!(env.get("Api_url"), Some("1".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_uppercase_is_ascii() {
+        let policy = KeyPolicy {
+            uppercase: true,
+            ..KeyPolicy::default()
+        };
+        assert_eq!(policy.normalize("stra\u{df}e"), "STRA\u{df}E");
+        assert!(policy.lint("STRA\u{df}E: 1\n").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "`BAD KEY` is not a valid environment variable name")]
+    fn test_set_rejects_invalid_keys() {
+        let mut env = LinoEnv::new("policy_set.lenv");
+        env.set_key_policy(KeyPolicy::strict());
+        env.set("bad key", "1");
+    }
+
+    #[test]
+    fn test_write_rejects_invalid_keys() {
+        let file = TempLenv::new();
+        let mut env = LinoEnv::new(&file);
+        assert!(env.set("bad key", "1").try_set("ok", "2").is_ok());
+        env.set_key_policy(KeyPolicy::strict());
+        assert!(env.try_set("bad key", "1").is_err());
+        assert!(env.write().is_err());
+        assert!(!Path::new(file.path()).exists());
+    }
+
+    #[test]
+    fn test_lint_case_conflicts() {
+        let policy = KeyPolicy {
+            case_insensitive: true,
+            ..KeyPolicy::default()
+        };
+        let errors = policy.lint("Api_Url: a\nAPI_URL: b\nApi_Url: c\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "line 2: `API_URL` differs from `Api_Url` only by case"
+        );
+    }
+}
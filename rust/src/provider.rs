@@ -0,0 +1,280 @@
+//! `.lenv` files as sources for the `figment` and `config` crates.
+//!
+//! [`LinoEnvProvider`] implements `figment::Provider` (with the `figment`
+//! feature) and `config::Source` (with the `config` feature). Like the
+//! environment providers of both crates it can keep only keys with a prefix,
+//! removing it, and split keys into nested paths; keys are lowercased.
+//! Values carry the path of the `.lenv` file as their origin, so errors name
+//! the file a bad value came from.
+//!
+//! ```
+//! # #[cfg(feature = "figment")] {
+//! use figment::Figment;
+//! use lino_env::provider::LinoEnvProvider;
+//! use lino_env::LinoEnv;
+//!
+//! let mut env = LinoEnv::new("app.lenv");
+//! env.set("APP_DB__HOST", "localhost").set("APP_DB__PORT", "5432").set("OTHER", "x");
+//!
+//! let figment = Figment::new().merge(LinoEnvProvider::from(env).prefix("APP_").split("__"));
+//! let port: u16 = figment.extract_inner("db.port").unwrap();
+//! assert_eq!(port, 5432);
+//! assert!(figment.find_value("other").is_err());
+//! # }
+//! ```
+
+use std::io;
+use std::path::Path;
+
+use crate::{read_lino_env, LinoEnv};
+
+/// A `.lenv` file or [`LinoEnv`] as a configuration source.
+#[derive(Debug, Clone)]
+pub struct LinoEnvProvider {
+    path: String,
+    env: Option<LinoEnv>,
+    prefix: Option<String>,
+    separator: Option<String>,
+    required: bool,
+}
+
+impl From<LinoEnv> for LinoEnvProvider {
+    /// Provide the values of `env`, with its file as their origin.
+    fn from(env: LinoEnv) -> Self {
+        Self {
+            path: env.file_path().to_string(),
+            env: Some(env),
+            prefix: None,
+            separator: None,
+            required: false,
+        }
+    }
+}
+
+impl LinoEnvProvider {
+    /// Provide the values of the file at `path`, read each time the
+    /// configuration is collected. A missing file provides nothing unless
+    /// [`required`](Self::required) is set.
+    #[must_use]
+    pub fn file<P: AsRef<str>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_string(),
+            env: None,
+            prefix: None,
+            separator: None,
+            required: false,
+        }
+    }
+
+    /// Keep only keys starting with `prefix`, and remove it from them.
+    #[must_use]
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Split keys at `separator` into nested paths, so `DB__HOST` with
+    /// separator `__` becomes `db.host`.
+    #[must_use]
+    pub fn split(mut self, separator: &str) -> Self {
+        self.separator = Some(separator.to_string());
+        self
+    }
+
+    /// Fail when the file does not exist.
+    #[must_use]
+    pub const fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Path of the `.lenv` file the values come from.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Lowercased key paths and values after prefix filtering and splitting,
+    /// in file order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or does not exist and
+    /// is required.
+    pub fn entries(&self) -> io::Result<Vec<(Vec<String>, String)>> {
+        let env = match &self.env {
+            Some(env) => env.clone(),
+            None if self.required && !Path::new(&self.path).exists() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{}: file not found", self.path),
+                ));
+            }
+            None => read_lino_env(&self.path)?,
+        };
+        let mut entries = Vec::new();
+        for key in env.keys() {
+            let name = match &self.prefix {
+                Some(prefix) => match key.strip_prefix(prefix.as_str()) {
+                    Some(name) => name,
+                    None => continue,
+                },
+                None => key.as_str(),
+            };
+            let name = name.to_lowercase();
+            let path: Vec<String> = match &self.separator {
+                Some(separator) => name.split(separator.as_str()).map(str::to_string).collect(),
+                None => vec![name],
+            };
+            if path.iter().any(String::is_empty) {
+                continue;
+            }
+            entries.push((path, env.get(&key).unwrap_or_default()));
+        }
+        Ok(entries)
+    }
+
+    /// The key as written in the file for a lowercased key path.
+    #[cfg(feature = "figment")]
+    fn original_key(&self, path: &[&str]) -> String {
+        let key = path.join(self.separator.as_deref().unwrap_or("."));
+        format!(
+            "{}{}",
+            self.prefix.as_deref().unwrap_or_default(),
+            key.to_uppercase()
+        )
+    }
+}
+
+#[cfg(feature = "figment")]
+mod figment_provider {
+    use figment::value::{Dict, Map, Value};
+    use figment::{Error, Metadata, Profile, Provider};
+
+    use super::{LinoEnvProvider, Path};
+
+    fn insert(dict: &mut Dict, path: &[String], value: Value) {
+        let Some((first, rest)) = path.split_first() else {
+            return;
+        };
+        if rest.is_empty() {
+            dict.insert(first.clone(), value);
+            return;
+        }
+        let child = dict
+            .entry(first.clone())
+            .or_insert_with(|| Dict::new().into());
+        if !matches!(child, Value::Dict(..)) {
+            *child = Dict::new().into();
+        }
+        if let Value::Dict(_, child) = child {
+            insert(child, rest, value);
+        }
+    }
+
+    impl Provider for LinoEnvProvider {
+        fn metadata(&self) -> Metadata {
+            let provider = self.clone();
+            let path = Path::new(&self.path);
+            Metadata::named("`.lenv` file")
+                .source(path.canonicalize().as_deref().unwrap_or(path))
+                .interpolater(move |_: &Profile, keys: &[&str]| provider.original_key(keys))
+        }
+
+        fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+            let entries = self.entries().map_err(|e| Error::from(e.to_string()))?;
+            let mut dict = Dict::new();
+            for (path, value) in entries {
+                let value = value.parse().unwrap_or_else(|never| match never {});
+                insert(&mut dict, &path, value);
+            }
+            Ok(Profile::Default.collect(dict))
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+mod config_source {
+    use ::config::{ConfigError, Map, Source, Value, ValueKind};
+
+    use super::LinoEnvProvider;
+
+    impl Source for LinoEnvProvider {
+        fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+            Box::new(self.clone())
+        }
+
+        /// Nested paths are joined with `.`, which `config` splits again.
+        fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+            let entries = self
+                .entries()
+                .map_err(|e| ConfigError::Foreign(Box::new(e)))?;
+            Ok(entries
+                .into_iter()
+                .map(|(path, value)| {
+                    let value = Value::new(Some(&self.path), ValueKind::String(value));
+                    (path.join("."), value)
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> LinoEnvProvider {
+        let mut env = LinoEnv::new("provider_test.lenv");
+        env.set("APP_NAME", "demo")
+            .set("APP_DB__HOST", "localhost")
+            .set("APP_DB__PORT", "5432")
+            .set("APP___BAD", "x")
+            .set("HOME", "/root");
+        LinoEnvProvider::from(env).prefix("APP_").split("__")
+    }
+
+    #[test]
+    fn test_entries() {
+        let entries = provider().entries().unwrap();
+        let paths: Vec<String> = entries.iter().map(|(path, _)| path.join(".")).collect();
+        assert_eq!(paths, vec!["name", "db.host", "db.port"]);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let provider = LinoEnvProvider::file("lino_env_test_provider_missing.lenv");
+        assert!(provider.entries().unwrap().is_empty());
+        assert!(provider.required(true).entries().is_err());
+    }
+
+    #[cfg(feature = "figment")]
+    #[test]
+    fn test_figment_provenance() {
+        use figment::Figment;
+
+        let figment = Figment::new().merge(provider());
+        assert_eq!(figment.extract_inner::<u16>("db.port").unwrap(), 5432);
+        let error = figment.extract_inner::<u16>("db.host").unwrap_err();
+        assert_eq!(error.path, vec!["db", "host"]);
+        let metadata = error.metadata.unwrap();
+        assert_eq!(metadata.name, "`.lenv` file");
+        assert_eq!(
+            metadata.interpolate(&figment::Profile::Default, &["db", "host"]),
+            "APP_DB__HOST"
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_source() {
+        let config = ::config::Config::builder()
+            .add_source(provider())
+            .build()
+            .unwrap();
+        assert_eq!(config.get::<u16>("db.port").unwrap(), 5432);
+        assert_eq!(config.get_string("name").unwrap(), "demo");
+        let error = config.get::<u16>("db.host").unwrap_err();
+        assert!(error.to_string().contains("provider_test.lenv"), "{error}");
+    }
+}
@@ -0,0 +1,662 @@
+//! Schema files describing the keys a `.lenv` file must provide.
+//!
+//! A schema is itself written in links notation: every unindented line names
+//! a key and the indented `property: value` lines below it describe that key.
+//!
+//! ```text
+//! # .lenv.schema
+//! DATABASE_URL
+//!   type: url
+//!   required: true
+//!   description: Primary database connection string
+//! PORT
+//!   type: int
+//!   default: 8080
+//! LOG_LEVEL
+//!   type: enum
+//!   values: debug info warn error
+//!   default: info
+//! API_KEY
+//!   type: regex
+//!   pattern: ^[a-z0-9]{32}$
+//! ```
+//!
+//! Supported properties are `type` (`string`, `int`, `bool`, `url`, `enum`,
+//! `regex`), `required`, `default`, `description`, `values` (space-separated,
+//! for `enum`) and `pattern` (for `regex`). `default:` with nothing after the
+//! colon is an empty default. A key line holds only the name, optionally
+//! followed by `:`; a line such as `PORT: 8080` is an error.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::LinoEnv;
+
+/// The type a value must have.
+#[derive(Debug, Clone)]
+pub enum KeyType {
+    /// Any value.
+    String,
+    /// A signed 64-bit integer.
+    Int,
+    /// `true`/`false`, `yes`/`no`, `on`/`off` or `1`/`0` (case-insensitive).
+    Bool,
+    /// An absolute URL such as `https://example.com:8080/path`.
+    Url,
+    /// One of a fixed list of values.
+    Enum(Vec<String>),
+    /// A value matching a regular expression in full.
+    Regex(Pattern),
+}
+
+/// A regular expression that must match a whole value.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Compile `pattern`, anchored at both ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::schema::Pattern;
+    /// let pattern = Pattern::new("a|ab").unwrap();
+    /// assert!(pattern.is_match("ab"));
+    /// assert!(!pattern.is_match("abc"));
+    /// ```
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            source: pattern.to_string(),
+            regex: Regex::new(&format!("^(?:{pattern})$"))?,
+        })
+    }
+
+    /// The pattern as written.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether `value` matches the pattern in full.
+    #[must_use]
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl KeyType {
+    /// Check whether `value` is valid for this type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::schema::KeyType;
+    /// assert!(KeyType::Int.accepts("-42"));
+    /// assert!(!KeyType::Bool.accepts("maybe"));
+    /// ```
+    #[must_use]
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            Self::String => true,
+            Self::Int => value.parse::<i64>().is_ok(),
+            Self::Bool => parse_bool(value).is_some(),
+            Self::Url => is_url(value),
+            Self::Enum(values) => values.iter().any(|allowed| allowed == value),
+            Self::Regex(pattern) => pattern.is_match(value),
+        }
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String => write!(f, "string"),
+            Self::Int => write!(f, "int"),
+            Self::Bool => write!(f, "bool"),
+            Self::Url => write!(f, "url"),
+            Self::Enum(values) => write!(f, "one of: {}", values.join(", ")),
+            Self::Regex(pattern) => write!(f, "a value matching `{}`", pattern.as_str()),
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn is_url(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once("://") else {
+        return false;
+    };
+    let mut scheme_chars = scheme.chars();
+    scheme_chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !rest.is_empty()
+        && !rest.starts_with('/')
+        && !value.chars().any(char::is_whitespace)
+}
+
+/// Declaration of a single key in a [`Schema`].
+#[derive(Debug, Clone)]
+pub struct KeySpec {
+    /// The key name.
+    pub name: String,
+    /// The type its value must have.
+    pub key_type: KeyType,
+    /// Whether the key must be present (or have a default).
+    pub required: bool,
+    /// Value used when the key is missing.
+    pub default: Option<String>,
+    /// Human-readable description.
+    pub description: Option<String>,
+}
+
+impl KeySpec {
+    /// Create an optional `string` key with no default or description.
+    #[must_use]
+    pub fn new<K: AsRef<str>>(name: K) -> Self {
+        Self {
+            name: name.as_ref().to_string(),
+            key_type: KeyType::String,
+            required: false,
+            default: None,
+            description: None,
+        }
+    }
+}
+
+/// A problem found by [`Schema::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// A required key without a default is missing.
+    MissingRequired { key: String },
+    /// A key's value does not match its declared type.
+    InvalidValue { key: String, expected: String },
+}
+
+impl Violation {
+    /// The key this violation refers to.
+    #[must_use]
+    pub fn key(&self) -> &str {
+        match self {
+            Self::MissingRequired { key } | Self::InvalidValue { key, .. } => key,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingRequired { key } => write!(f, "{key}: required key is missing"),
+            Self::InvalidValue { key, expected } => write!(f, "{key}: expected {expected}"),
+        }
+    }
+}
+
+/// Error returned when a schema cannot be loaded.
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The schema file could not be read.
+    Io(io::Error),
+    /// The schema text is malformed.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl Error for SchemaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for SchemaError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[derive(Default)]
+struct PendingSpec {
+    line: usize,
+    name: String,
+    key_type: Option<String>,
+    values: Option<Vec<String>>,
+    pattern: Option<String>,
+    required: bool,
+    default: Option<String>,
+    description: Option<String>,
+}
+
+impl PendingSpec {
+    fn finish(self) -> Result<KeySpec, SchemaError> {
+        let error = |message: String| SchemaError::Parse {
+            line: self.line,
+            message,
+        };
+        let key_type = match self.key_type.as_deref().unwrap_or("string") {
+            "string" => KeyType::String,
+            "int" => KeyType::Int,
+            "bool" => KeyType::Bool,
+            "url" => KeyType::Url,
+            "enum" => KeyType::Enum(
+                self.values
+                    .ok_or_else(|| error(format!("{}: enum type requires `values`", self.name)))?,
+            ),
+            "regex" => {
+                let pattern = self.pattern.ok_or_else(|| {
+                    error(format!("{}: regex type requires `pattern`", self.name))
+                })?;
+                KeyType::Regex(
+                    Pattern::new(&pattern)
+                        .map_err(|e| error(format!("{}: invalid pattern: {e}", self.name)))?,
+                )
+            }
+            other => return Err(error(format!("{}: unknown type `{other}`", self.name))),
+        };
+
+        if let Some(default) = &self.default {
+            if !key_type.accepts(default) {
+                return Err(error(format!(
+                    "{}: default does not match type {key_type}",
+                    self.name
+                )));
+            }
+        }
+
+        Ok(KeySpec {
+            name: self.name,
+            key_type,
+            required: self.required,
+            default: self.default,
+            description: self.description,
+        })
+    }
+}
+
+/// A set of key declarations loaded from a `.lenv.schema` file.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::schema::Schema;
+/// use lino_env::LinoEnv;
+///
+/// let schema = Schema::parse("PORT\n  type: int\n  required: true\n").unwrap();
+///
+/// let mut env = LinoEnv::new(".lenv");
+/// env.set("PORT", "eighty");
+/// let violations = schema.validate(&env);
+/// assert_eq!(violations[0].to_string(), "PORT: expected int");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    keys: Vec<KeySpec>,
+}
+
+impl Schema {
+    /// Create an empty schema.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// Parse schema text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::Parse`] for unknown properties or types,
+    /// invalid patterns, and defaults that do not match their type.
+    pub fn parse(input: &str) -> Result<Self, SchemaError> {
+        let mut schema = Self::new();
+        let mut current: Option<PendingSpec> = None;
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: String| SchemaError::Parse {
+                line: line_number,
+                message,
+            };
+
+            if !line.starts_with(char::is_whitespace) {
+                let name = trimmed.trim_end_matches(':').trim_end();
+                if name.is_empty() || name.contains(": ") {
+                    return Err(error(format!("expected a key name, got `{trimmed}`")));
+                }
+                if let Some(spec) = current.take() {
+                    schema.add(spec.finish()?);
+                }
+                current = Some(PendingSpec {
+                    line: line_number,
+                    name: name.to_string(),
+                    ..PendingSpec::default()
+                });
+                continue;
+            }
+
+            let spec = current
+                .as_mut()
+                .ok_or_else(|| error("property without a key".to_string()))?;
+            // `default:` with nothing after it is an empty value.
+            let (property, value) = trimmed
+                .split_once(": ")
+                .or_else(|| trimmed.strip_suffix(':').map(|property| (property, "")))
+                .ok_or_else(|| error(format!("expected `property: value`, got `{trimmed}`")))?;
+            let value = value.trim();
+            match property.trim() {
+                "type" => spec.key_type = Some(value.to_string()),
+                "required" => {
+                    spec.required = parse_bool(value)
+                        .ok_or_else(|| error(format!("invalid boolean `{value}`")))?;
+                }
+                "default" => spec.default = Some(value.to_string()),
+                "description" => spec.description = Some(value.to_string()),
+                "values" => {
+                    spec.values = Some(value.split_whitespace().map(str::to_string).collect());
+                }
+                "pattern" => spec.pattern = Some(value.to_string()),
+                other => return Err(error(format!("unknown property `{other}`"))),
+            }
+        }
+
+        if let Some(spec) = current {
+            schema.add(spec.finish()?);
+        }
+
+        Ok(schema)
+    }
+
+    /// Read and parse a schema file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, SchemaError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Add or replace a key declaration.
+    pub fn add(&mut self, spec: KeySpec) -> &mut Self {
+        if let Some(existing) = self.keys.iter_mut().find(|key| key.name == spec.name) {
+            *existing = spec;
+        } else {
+            self.keys.push(spec);
+        }
+        self
+    }
+
+    /// Look up the declaration of a key.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&KeySpec> {
+        self.keys.iter().find(|key| key.name == name)
+    }
+
+    /// All key declarations, in schema order.
+    #[must_use]
+    pub fn keys(&self) -> &[KeySpec] {
+        &self.keys
+    }
+
+    /// Check `env` against the schema and return every problem found.
+    ///
+    /// Keys not declared in the schema are ignored. A required key with a
+    /// default is not reported as missing, since [`Schema::apply_defaults`]
+    /// fills it in.
+    #[must_use]
+    pub fn validate(&self, env: &LinoEnv) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for spec in &self.keys {
+            match env.get(&spec.name) {
+                Some(value) if !spec.key_type.accepts(&value) => {
+                    violations.push(Violation::InvalidValue {
+                        key: spec.name.clone(),
+                        expected: spec.key_type.to_string(),
+                    });
+                }
+                None if spec.required && spec.default.is_none() => {
+                    violations.push(Violation::MissingRequired {
+                        key: spec.name.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        violations
+    }
+
+    /// Set every missing key that has a default to that default.
+    ///
+    /// # Returns
+    ///
+    /// The keys that were filled in.
+    pub fn apply_defaults(&self, env: &mut LinoEnv) -> Vec<String> {
+        let mut applied = Vec::new();
+        for spec in &self.keys {
+            if let Some(default) = &spec.default {
+                if !env.has(&spec.name) {
+                    env.set(&spec.name, default);
+                    applied.push(spec.name.clone());
+                }
+            }
+        }
+        applied
+    }
+}
+
+/// Write an indented `property: value` line, or `property:` for an empty
+/// value, which [`Schema::parse`] reads back as empty.
+fn write_property(f: &mut fmt::Formatter<'_>, property: &str, value: &str) -> fmt::Result {
+    if value.is_empty() {
+        writeln!(f, "  {property}:")
+    } else {
+        writeln!(f, "  {property}: {value}")
+    }
+}
+
+impl fmt::Display for Schema {
+    /// Write the schema in the format read by [`Schema::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for spec in &self.keys {
+            writeln!(f, "{}", spec.name)?;
+            match &spec.key_type {
+                KeyType::Enum(values) => {
+                    write_property(f, "type", "enum")?;
+                    write_property(f, "values", &values.join(" "))?;
+                }
+                KeyType::Regex(pattern) => {
+                    write_property(f, "type", "regex")?;
+                    write_property(f, "pattern", pattern.as_str())?;
+                }
+                key_type => write_property(f, "type", &key_type.to_string())?,
+            }
+            if spec.required {
+                write_property(f, "required", "true")?;
+            }
+            if let Some(default) = &spec.default {
+                write_property(f, "default", default)?;
+            }
+            if let Some(description) = &spec.description {
+                write_property(f, "description", description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "\
+# Example schema
+DATABASE_URL
+  type: url
+  required: true
+  description: Primary database
+PORT:
+  type: int
+  default: 8080
+LOG_LEVEL
+  type: enum
+  values: debug info warn
+DEBUG
+  type: bool
+API_KEY
+  type: regex
+  pattern: [a-z0-9]{4}
+";
+
+    fn env_with(pairs: &[(&str, &str)]) -> LinoEnv {
+        let mut env = LinoEnv::new("schema_test.lenv");
+        for (key, value) in pairs {
+            env.set(key, value);
+        }
+        env
+    }
+
+    #[test]
+    fn test_parse() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let names: Vec<&str> = schema.keys().iter().map(|key| key.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["DATABASE_URL", "PORT", "LOG_LEVEL", "DEBUG", "API_KEY"]
+        );
+        let database = schema.get("DATABASE_URL").unwrap();
+        assert!(database.required);
+        assert_eq!(database.description.as_deref(), Some("Primary database"));
+        assert_eq!(schema.get("PORT").unwrap().default.as_deref(), Some("8080"));
+    }
+
+    #[test]
+    fn test_validate_reports_all_violations() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let env = env_with(&[
+            ("PORT", "eighty"),
+            ("LOG_LEVEL", "trace"),
+            ("DEBUG", "yes"),
+            ("API_KEY", "abcd1"),
+        ]);
+        let violations = schema.validate(&env);
+        let keys: Vec<&str> = violations.iter().map(Violation::key).collect();
+        assert_eq!(keys, vec!["DATABASE_URL", "PORT", "LOG_LEVEL", "API_KEY"]);
+        assert_eq!(
+            violations[0],
+            Violation::MissingRequired {
+                key: "DATABASE_URL".to_string()
+            }
+        );
+        assert_eq!(
+            violations[2].to_string(),
+            "LOG_LEVEL: expected one of: debug, info, warn"
+        );
+    }
+
+    #[test]
+    fn test_valid_env() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let env = env_with(&[
+            ("DATABASE_URL", "postgres://localhost:5432/app"),
+            ("API_KEY", "ab12"),
+        ]);
+        assert!(schema.validate(&env).is_empty());
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let mut env = env_with(&[]);
+        assert_eq!(schema.apply_defaults(&mut env), vec!["PORT".to_string()]);
+        assert_eq!(env.get("PORT"), Some("8080".to_string()));
+    }
+
+    #[test]
+    fn test_url() {
+        assert!(KeyType::Url.accepts("https://example.com:8080/path"));
+        assert!(!KeyType::Url.accepts("example.com"));
+        assert!(!KeyType::Url.accepts("https:///path"));
+        assert!(!KeyType::Url.accepts("1http://example.com"));
+    }
+
+    #[test]
+    fn test_regex_matches_in_full() {
+        let schema = Schema::parse("MODE\n  type: regex\n  pattern: a|ab\n").unwrap();
+        let key_type = &schema.keys[0].key_type;
+        assert!(key_type.accepts("a"));
+        assert!(key_type.accepts("ab"));
+        assert!(!key_type.accepts("abc"));
+        assert!(!key_type.accepts("xab"));
+        assert!(schema.to_string().contains("  pattern: a|ab\n"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = Schema::parse("PORT\n  type: float\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: PORT: unknown type `float`");
+        let error = Schema::parse("PORT\n  kind: int\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: unknown property `kind`");
+        let error = Schema::parse("PORT: 8080\n  type: int\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: expected a key name, got `PORT: 8080`"
+        );
+        let error = Schema::parse("PORT\n  type: int\n  default: x\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: PORT: default does not match type int"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let text = schema.to_string();
+        assert!(text.starts_with(
+            "DATABASE_URL\n  type: url\n  required: true\n  description: Primary database\n"
+        ));
+        assert_eq!(Schema::parse(&text).unwrap().to_string(), text);
+    }
+
+    #[test]
+    fn test_empty_values_round_trip() {
+        let text = "PREFIX\n  type: string\n  default:\n  description:\n";
+        let schema = Schema::parse(text).unwrap();
+        let prefix = schema.get("PREFIX").unwrap();
+        assert_eq!(prefix.default.as_deref(), Some(""));
+        assert_eq!(prefix.description.as_deref(), Some(""));
+        assert_eq!(schema.to_string(), text);
+        assert_eq!(
+            Schema::parse("PREFIX\n  default: \n").unwrap().to_string(),
+            "PREFIX\n  type: string\n  default:\n"
+        );
+    }
+}
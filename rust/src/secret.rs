@@ -0,0 +1,109 @@
+//! Secret-safe values and sensitive key detection.
+//!
+//! [`SecretValue`] prints as `***` and wipes its buffer when dropped, so a
+//! token can be passed around and logged by accident without leaking.
+//! Keys matching the sensitive patterns of a [`LinoEnv`](crate::LinoEnv)
+//! (by default `*_TOKEN`, `*_SECRET` and `*_PASSWORD`) are also redacted
+//! from its `Debug` output.
+
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+use crate::glob::glob_match;
+
+/// Key patterns treated as sensitive unless configured otherwise.
+pub const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &["*_TOKEN", "*_SECRET", "*_PASSWORD"];
+
+/// Text printed instead of a secret.
+pub const REDACTED: &str = "***";
+
+/// A value that is redacted in `Debug`/`Display` and zeroized on drop.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::LinoEnv;
+///
+/// let mut env = LinoEnv::new(".lenv");
+/// env.set("GITHUB_TOKEN", "gh_abc123");
+///
+/// let token = env.get_secret("GITHUB_TOKEN").unwrap();
+/// assert_eq!(format!("{token} {token:?}"), "*** ***");
+/// assert_eq!(token.expose(), "gh_abc123");
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretValue(Zeroizing<String>);
+
+impl SecretValue {
+    /// Wrap a value.
+    #[must_use]
+    pub fn new(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+
+    /// Access the underlying value.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretValue {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for SecretValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl fmt::Display for SecretValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Match `key` against a glob `pattern` where `*` matches any sequence.
+///
+/// Matching ignores ASCII case.
+///
+/// # Examples
+///
+/// ```
+/// use lino_env::secret::matches_pattern;
+/// assert!(matches_pattern("*_TOKEN", "GITHUB_TOKEN"));
+/// assert!(matches_pattern("DB_*", "db_password"));
+/// assert!(!matches_pattern("*_TOKEN", "TOKEN_URL"));
+/// ```
+#[must_use]
+pub fn matches_pattern(pattern: &str, key: &str) -> bool {
+    glob_match(&pattern.to_ascii_uppercase(), &key.to_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*_TOKEN", "GITHUB_TOKEN"));
+        assert!(matches_pattern("*", "ANYTHING"));
+        assert!(matches_pattern("API_KEY", "api_key"));
+        assert!(matches_pattern("*_DB_*", "MAIN_DB_PASSWORD"));
+        assert!(!matches_pattern("API_KEY", "API_KEYS"));
+        assert!(!matches_pattern("*_TOKEN", "_TOKEN_"));
+        assert!(!matches_pattern("A*A", "A"));
+    }
+
+    #[test]
+    fn test_secret_value_redacted() {
+        let value = SecretValue::from("hunter2".to_string());
+        assert_eq!(format!("{value}"), REDACTED);
+        assert_eq!(format!("{value:?}"), REDACTED);
+        assert_eq!(value.expose(), "hunter2");
+    }
+}
@@ -0,0 +1,260 @@
+//! Environment lookups without `std::env::set_var`.
+//!
+//! Calling `set_var` while other threads read the environment is undefined
+//! behavior on most platforms, so copying a [`LinoEnv`] into the process
+//! environment is not a safe way to hand it to the rest of a program.
+//! [`EnvSource`] abstracts over where values come from instead: the process
+//! environment ([`ProcessEnv`]), a [`LinoEnv`], or a [`Layered`] stack of
+//! both. [`install`] makes a [`Snapshot`] of a source visible process-wide,
+//! and [`var`] reads from it:
+//!
+//! ```
+//! use lino_env::source::{install, Layered, ProcessEnv};
+//! use lino_env::LinoEnv;
+//!
+//! let mut defaults = LinoEnv::new(".lenv");
+//! defaults.set("LINO_ENV_DOC_API_URL", "https://api.example.com");
+//!
+//! // `.lenv` values, overridden by the real environment.
+//! install(&Layered::new().layer(defaults).layer(ProcessEnv));
+//! assert_eq!(
+//!     lino_env::var("LINO_ENV_DOC_API_URL").as_deref(),
+//!     Some("https://api.example.com")
+//! );
+//! assert!(std::env::var("LINO_ENV_DOC_API_URL").is_err());
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::LinoEnv;
+
+/// Something environment values can be looked up in.
+///
+/// A slice of sources is a stack like [`Layered`]: a key takes its value
+/// from the last source that sets it.
+pub trait EnvSource {
+    /// Value of `key`, if set.
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Every key with a value.
+    fn keys(&self) -> Vec<String>;
+}
+
+impl<T: EnvSource + ?Sized> EnvSource for &T {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        (**self).keys()
+    }
+}
+
+impl<T: EnvSource + ?Sized> EnvSource for Box<T> {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        (**self).keys()
+    }
+}
+
+impl<T: EnvSource> EnvSource for [T] {
+    fn get(&self, key: &str) -> Option<String> {
+        self.iter().rev().find_map(|source| source.get(key))
+    }
+
+    fn keys(&self) -> Vec<String> {
+        unique_keys(self.iter())
+    }
+}
+
+impl EnvSource for LinoEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        Self::get(self, key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        Self::keys(self)
+    }
+}
+
+/// The environment of the process, read at each lookup. Keys and values
+/// that are not valid UTF-8 are skipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn get(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+
+    fn keys(&self) -> Vec<String> {
+        env::vars_os()
+            .filter_map(|(key, value)| {
+                value.to_str()?;
+                key.into_string().ok()
+            })
+            .collect()
+    }
+}
+
+/// Sources stacked on top of each other: a key takes its value from the
+/// last layer that sets it.
+#[derive(Default)]
+pub struct Layered {
+    layers: Vec<Box<dyn EnvSource + Send + Sync>>,
+}
+
+impl Layered {
+    /// An empty stack.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `source` on top of the existing layers.
+    #[must_use]
+    pub fn layer<S: EnvSource + Send + Sync + 'static>(mut self, source: S) -> Self {
+        self.layers.push(Box::new(source));
+        self
+    }
+}
+
+impl EnvSource for Layered {
+    fn get(&self, key: &str) -> Option<String> {
+        self.layers.iter().rev().find_map(|layer| layer.get(key))
+    }
+
+    /// Keys of all layers, in layer order, without duplicates.
+    fn keys(&self) -> Vec<String> {
+        unique_keys(self.layers.iter())
+    }
+}
+
+fn unique_keys<'a, S: EnvSource + 'a>(sources: impl Iterator<Item = &'a S>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    sources
+        .flat_map(EnvSource::keys)
+        .filter(|key| seen.insert(key.clone()))
+        .collect()
+}
+
+/// The values of a source, copied at one point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    values: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    /// Copy every value of `source`.
+    #[must_use]
+    pub fn capture<S: EnvSource + ?Sized>(source: &S) -> Self {
+        let values = source
+            .keys()
+            .into_iter()
+            .filter_map(|key| Some((key.clone(), source.get(&key)?)))
+            .collect();
+        Self { values }
+    }
+}
+
+impl EnvSource for Snapshot {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    /// Keys in sorted order.
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+}
+
+static INSTALLED: RwLock<Option<Arc<Snapshot>>> = RwLock::new(None);
+
+/// Make a snapshot of `source` the one read by [`var`], replacing any
+/// previous one. Returns the snapshot.
+pub fn install<S: EnvSource + ?Sized>(source: &S) -> Arc<Snapshot> {
+    let snapshot = Arc::new(Snapshot::capture(source));
+    *INSTALLED.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::clone(&snapshot));
+    snapshot
+}
+
+/// The snapshot read by [`var`], if one is installed.
+#[must_use]
+pub fn installed() -> Option<Arc<Snapshot>> {
+    INSTALLED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Value of `key` in the installed snapshot, or in the process environment
+/// if none is installed.
+#[must_use]
+pub fn var(key: &str) -> Option<String> {
+    installed().map_or_else(|| ProcessEnv.get(key), |snapshot| snapshot.get(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::EnvGuard;
+
+    fn lenv(entries: &[(&str, &str)]) -> LinoEnv {
+        let mut env = LinoEnv::new("source_test.lenv");
+        for (key, value) in entries {
+            env.set(key, value);
+        }
+        env
+    }
+
+    #[test]
+    fn test_layered() {
+        let layered = Layered::new()
+            .layer(lenv(&[("A", "base"), ("B", "base")]))
+            .layer(lenv(&[("B", "local"), ("C", "local")]));
+        assert_eq!(layered.get("A").as_deref(), Some("base"));
+        assert_eq!(layered.get("B").as_deref(), Some("local"));
+        assert_eq!(layered.get("D"), None);
+        assert_eq!(layered.keys(), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_process_env_on_top() {
+        let _guard = EnvGuard::new();
+        let layered = Layered::new()
+            .layer(lenv(&[("PATH", "from-lenv")]))
+            .layer(ProcessEnv);
+        assert_eq!(layered.get("PATH"), env::var("PATH").ok());
+    }
+
+    #[test]
+    fn test_snapshot_is_a_copy() {
+        let mut source = lenv(&[("A", "1")]);
+        let snapshot = Snapshot::capture(&source);
+        source.set("A", "2");
+        assert_eq!(snapshot.get("A").as_deref(), Some("1"));
+        assert_eq!(snapshot.keys(), vec!["A"]);
+    }
+
+    // The only test touching the process-wide snapshot, so that parallel
+    // tests cannot observe each other's installs.
+    #[test]
+    fn test_install_and_var() {
+        let _guard = EnvGuard::new();
+        install(&lenv(&[("LINO_ENV_TEST_SOURCE_VAR", "installed")]));
+        assert_eq!(
+            var("LINO_ENV_TEST_SOURCE_VAR").as_deref(),
+            Some("installed")
+        );
+        assert!(env::var("LINO_ENV_TEST_SOURCE_VAR").is_err());
+        assert_eq!(
+            installed().unwrap().keys(),
+            vec!["LINO_ENV_TEST_SOURCE_VAR"]
+        );
+    }
+}
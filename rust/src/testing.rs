@@ -0,0 +1,295 @@
+//! Helpers for testing code that loads `.lenv` files.
+//!
+//! [`TempLenv`] is a `.lenv` file in the temporary directory with a path no
+//! other test uses, removed when it is dropped, even if the test panics.
+//! [`TempDir`] is the same for a directory, for tests that need several
+//! files side by side.
+//!
+//! [`EnvGuard`] sets process environment variables for the length of a test
+//! and restores the previous values when dropped. Guards take a process-wide
+//! lock, so only one exists at a time. The lock does not stop threads that
+//! read the environment without a guard, and changing the environment while
+//! another thread reads it is undefined behavior on most platforms (see
+//! [`source`](crate::source)): every test that reads the environment must
+//! hold a guard too, or read values through an
+//! [`EnvSource`](crate::EnvSource) instead.
+//!
+//! ```
+//! use lino_env::testing::{EnvGuard, TempLenv};
+//! use lino_env::read_lino_env;
+//!
+//! let file = TempLenv::with_entries([("API_URL", "https://api.example.com")]).unwrap();
+//! let env = read_lino_env(&file).unwrap();
+//! assert_eq!(env.get("API_URL").as_deref(), Some("https://api.example.com"));
+//!
+//! {
+//!     let _guard = EnvGuard::apply(&env);
+//!     assert_eq!(std::env::var("API_URL").as_deref(), Ok("https://api.example.com"));
+//! }
+//! assert!(std::env::var("API_URL").is_err());
+//! ```
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::source::EnvSource;
+use crate::{read_lino_env, LinoEnv};
+
+/// A path in the temporary directory that no other test uses.
+fn unique_path(suffix: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = format!(
+        "lino_env_{}_{}{suffix}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    env::temp_dir().join(name)
+}
+
+/// A `.lenv` file with a unique path, removed on drop together with its
+/// `.keys` file, if one was created.
+#[derive(Debug)]
+pub struct TempLenv {
+    path: String,
+}
+
+impl TempLenv {
+    /// Reserve a unique path without creating the file, for tests that
+    /// write it themselves.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            path: unique_path(".lenv").to_string_lossy().to_string(),
+        }
+    }
+
+    /// Create a file with `entries`, written like [`LinoEnv::write`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn with_entries<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> io::Result<Self>
+    where
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let file = Self::new();
+        let mut env = LinoEnv::new(&file.path);
+        for (key, value) in entries {
+            env.set(key.as_ref(), value.as_ref());
+        }
+        env.write()?;
+        Ok(file)
+    }
+
+    /// Create a file containing exactly `contents`, for tests of malformed
+    /// or hand-formatted input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn with_contents(contents: &str) -> io::Result<Self> {
+        let file = Self::new();
+        fs::write(&file.path, contents)?;
+        Ok(file)
+    }
+
+    /// Path of the file.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Read the file, which is empty if it was never created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn read(&self) -> io::Result<LinoEnv> {
+        read_lino_env(&self.path)
+    }
+}
+
+impl Default for TempLenv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsRef<str> for TempLenv {
+    fn as_ref(&self) -> &str {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempLenv {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.path)
+    }
+}
+
+impl Drop for TempLenv {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+        fs::remove_file(format!("{}.keys", self.path)).ok();
+    }
+}
+
+/// A directory with a unique path, removed with its contents on drop.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create an empty directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn new() -> io::Result<Self> {
+        let path = unique_path("");
+        fs::create_dir(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Path of the directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of `name` inside the directory.
+    #[must_use]
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Process environment changes undone on drop.
+///
+/// Only one guard exists at a time: creating a second one waits until the
+/// first is dropped, so a test needing several changes should make them
+/// through [`EnvGuard::set`] and [`EnvGuard::remove`] on one guard. Tests
+/// that only read the environment hold a guard from [`EnvGuard::new`].
+#[derive(Debug)]
+pub struct EnvGuard {
+    previous: Vec<(String, Option<OsString>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl EnvGuard {
+    /// A guard that has not changed anything yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            _lock: ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+
+    /// Set every key of `source` in the process environment.
+    #[must_use]
+    pub fn apply<S: EnvSource + ?Sized>(source: &S) -> Self {
+        let mut guard = Self::new();
+        for key in source.keys() {
+            if let Some(value) = source.get(&key) {
+                guard.set(&key, &value);
+            }
+        }
+        guard
+    }
+
+    /// Set `key` until the guard is dropped.
+    pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+        self.remember(key);
+        env::set_var(key, value);
+        self
+    }
+
+    /// Unset `key` until the guard is dropped.
+    pub fn remove(&mut self, key: &str) -> &mut Self {
+        self.remember(key);
+        env::remove_var(key);
+        self
+    }
+
+    fn remember(&mut self, key: &str) {
+        if !self.previous.iter().any(|(name, _)| name == key) {
+            self.previous.push((key.to_string(), env::var_os(key)));
+        }
+    }
+}
+
+impl Default for EnvGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for (key, value) in self.previous.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temp_lenv_is_removed() {
+        let file = TempLenv::with_entries([("A", "1"), ("B", "two words")]).unwrap();
+        let path = file.path().to_string();
+        assert_eq!(file.read().unwrap().get("B").as_deref(), Some("two words"));
+        assert_ne!(path, TempLenv::new().path());
+        drop(file);
+        assert!(!Path::new(&path).exists());
+
+        let file = TempLenv::with_contents("broken\n").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "broken\n");
+    }
+
+    #[test]
+    fn test_env_guard_restores() {
+        let key = "LINO_ENV_TEST_GUARD";
+        {
+            let mut guard = EnvGuard::new();
+            guard.set(key, "outer");
+        }
+        assert!(env::var(key).is_err());
+
+        // Set by Cargo when running tests, and read by no other test.
+        let existing = "CARGO_PKG_NAME";
+        let previous = env::var_os(existing);
+        let mut lenv = LinoEnv::new("guard_test.lenv");
+        lenv.set(key, "applied").set(existing, "changed");
+        {
+            let mut guard = EnvGuard::apply(&lenv);
+            assert_eq!(env::var(key).as_deref(), Ok("applied"));
+            assert_eq!(env::var(existing).as_deref(), Ok("changed"));
+            guard.set(key, "again").remove(existing);
+            assert!(env::var_os(existing).is_none());
+        }
+        assert!(env::var(key).is_err());
+        assert_eq!(env::var_os(existing), previous);
+    }
+}
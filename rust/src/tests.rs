@@ -0,0 +1,561 @@
+use super::*;
+use crate::testing::TempLenv;
+use std::fs;
+
+mod basic_tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_write() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("GITHUB_TOKEN", "gh_test123");
+        env.set("TELEGRAM_TOKEN", "054test456");
+        env.write().unwrap();
+
+        assert!(Path::new(test_file.path()).exists());
+    }
+
+    #[test]
+    fn test_read() {
+        let test_file = TempLenv::new();
+        // First create a file
+        let mut env1 = LinoEnv::new(&test_file);
+        env1.set("GITHUB_TOKEN", "gh_test123");
+        env1.set("TELEGRAM_TOKEN", "054test456");
+        env1.write().unwrap();
+
+        // Then read it
+        let mut env2 = LinoEnv::new(&test_file);
+        env2.read().unwrap();
+
+        assert_eq!(env2.get("GITHUB_TOKEN"), Some("gh_test123".to_string()));
+        assert_eq!(env2.get("TELEGRAM_TOKEN"), Some("054test456".to_string()));
+    }
+}
+
+mod get_tests {
+    use super::*;
+
+    #[test]
+    fn test_get_value() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("API_KEY", "value1");
+
+        assert_eq!(env.get("API_KEY"), Some("value1".to_string()));
+    }
+
+    #[test]
+    fn test_get_nonexistent() {
+        let test_file = TempLenv::new();
+        let env = LinoEnv::new(&test_file);
+        assert_eq!(env.get("NON_EXISTENT"), None);
+    }
+}
+
+mod set_tests {
+    use super::*;
+
+    #[test]
+    fn test_set_overwrites() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("API_KEY", "value1");
+        env.set("API_KEY", "new_value");
+
+        assert_eq!(env.get("API_KEY"), Some("new_value".to_string()));
+    }
+}
+
+mod duplicate_key_tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_keys_last_value_wins() {
+        let test_file = TempLenv::new();
+        // Write a file with duplicate keys manually
+        fs::write(&test_file, "A: value1\nA: value2\n").unwrap();
+
+        let mut env = LinoEnv::new(&test_file);
+        env.read().unwrap();
+
+        assert_eq!(env.get("A"), Some("value2".to_string()));
+    }
+}
+
+mod has_tests {
+    use super::*;
+
+    #[test]
+    fn test_has_existing() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("KEY", "value");
+
+        assert!(env.has("KEY"));
+    }
+
+    #[test]
+    fn test_has_nonexistent() {
+        let test_file = TempLenv::new();
+        let env = LinoEnv::new(&test_file);
+        assert!(!env.has("NON_EXISTENT"));
+    }
+}
+
+mod delete_tests {
+    use super::*;
+
+    #[test]
+    fn test_delete() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("KEY", "value1");
+        env.delete("KEY");
+
+        assert!(!env.has("KEY"));
+        assert_eq!(env.get("KEY"), None);
+    }
+}
+
+mod keys_tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("KEY1", "value1");
+        env.set("KEY2", "value2");
+        env.set("KEY3", "value3");
+
+        let keys = env.keys();
+        assert!(keys.contains(&"KEY1".to_string()));
+        assert!(keys.contains(&"KEY2".to_string()));
+        assert!(keys.contains(&"KEY3".to_string()));
+        assert_eq!(keys.len(), 3);
+    }
+}
+
+mod to_hash_map_tests {
+    use super::*;
+
+    #[test]
+    fn test_to_hash_map() {
+        let test_file = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file);
+        env.set("KEY1", "value1");
+        env.set("KEY2", "value2");
+
+        let obj = env.to_hash_map();
+        assert_eq!(obj.get("KEY1"), Some(&"value1".to_string()));
+        assert_eq!(obj.get("KEY2"), Some(&"value2".to_string()));
+    }
+}
+
+mod persistence_tests {
+    use super::*;
+
+    #[test]
+    fn test_persist_values() {
+        let test_file = TempLenv::new();
+        let mut env1 = LinoEnv::new(&test_file);
+        env1.set("KEY", "value");
+        env1.write().unwrap();
+
+        let mut env2 = LinoEnv::new(&test_file);
+        env2.read().unwrap();
+
+        assert_eq!(env2.get("KEY"), Some("value".to_string()));
+    }
+}
+
+mod convenience_function_tests {
+    use super::*;
+
+    #[test]
+    fn test_read_lino_env() {
+        let test_file_path = TempLenv::new();
+        let mut data = HashMap::new();
+        data.insert("GITHUB_TOKEN".to_string(), "gh_test".to_string());
+        data.insert("TELEGRAM_TOKEN".to_string(), "054test".to_string());
+        write_lino_env(&test_file_path, &data).unwrap();
+
+        let env = read_lino_env(&test_file_path).unwrap();
+        assert_eq!(env.get("GITHUB_TOKEN"), Some("gh_test".to_string()));
+        assert_eq!(env.get("TELEGRAM_TOKEN"), Some("054test".to_string()));
+    }
+
+    #[test]
+    fn test_write_lino_env() {
+        let test_file_path = TempLenv::new();
+        let mut data = HashMap::new();
+        data.insert("API_KEY".to_string(), "test_key".to_string());
+        data.insert("SECRET".to_string(), "test_secret".to_string());
+        write_lino_env(&test_file_path, &data).unwrap();
+
+        let env = read_lino_env(&test_file_path).unwrap();
+        assert_eq!(env.get("API_KEY"), Some("test_key".to_string()));
+        assert_eq!(env.get("SECRET"), Some("test_secret".to_string()));
+    }
+}
+
+mod format_tests {
+    use super::*;
+
+    #[test]
+    fn test_values_with_colons() {
+        let test_file_path = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file_path);
+        env.set("URL", "https://example.com:8080");
+        env.write().unwrap();
+
+        let mut env2 = LinoEnv::new(&test_file_path);
+        env2.read().unwrap();
+        assert_eq!(
+            env2.get("URL"),
+            Some("https://example.com:8080".to_string())
+        );
+    }
+
+    #[test]
+    fn test_values_with_spaces() {
+        let test_file_path = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file_path);
+        env.set("MESSAGE", "Hello World");
+        env.write().unwrap();
+
+        let mut env2 = LinoEnv::new(&test_file_path);
+        env2.read().unwrap();
+        assert_eq!(env2.get("MESSAGE"), Some("Hello World".to_string()));
+    }
+}
+
+mod multiline_quoted_value_tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_line_double_quoted_values() {
+        let test_file_path = TempLenv::new();
+        fs::write(
+            &test_file_path,
+            "HIVE_TELEGRAM_BOT_CONFIGURATION: \"\nTELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true\n\"\nAFTER: value\n",
+        )
+        .unwrap();
+
+        let mut env = LinoEnv::new(&test_file_path);
+        env.read().unwrap();
+
+        assert_eq!(
+            env.get("HIVE_TELEGRAM_BOT_CONFIGURATION"),
+            Some(
+                "\nTELEGRAM_BOT_TOKEN: 'xxx'\nTELEGRAM_ALLOWED_CHATS:\n  -1002975819706\nTELEGRAM_BOT_VERBOSE: true\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(env.get("TELEGRAM_BOT_TOKEN"), None);
+        assert_eq!(env.get("AFTER"), Some("value".to_string()));
+    }
+
+    #[test]
+    fn test_multi_line_single_quoted_values() {
+        let test_file_path = TempLenv::new();
+        fs::write(&test_file_path, "SCRIPT: 'line1\nline2'\nAFTER: value\n").unwrap();
+
+        let mut env = LinoEnv::new(&test_file_path);
+        env.read().unwrap();
+
+        assert_eq!(env.get("SCRIPT"), Some("line1\nline2".to_string()));
+        assert_eq!(env.get("AFTER"), Some("value".to_string()));
+    }
+}
+
+mod nested_value {
+    use super::*;
+
+    #[test]
+    fn test_nested_block_round_trip() {
+        let test_file_path = TempLenv::new();
+        let source = "BOT:\n  TOKEN: xxx\n  CHATS:\n    -100\n";
+        fs::write(&test_file_path, source).unwrap();
+
+        let mut env = LinoEnv::new(&test_file_path);
+        env.read().unwrap();
+        assert_eq!(
+            env.get("BOT"),
+            Some("TOKEN: xxx\nCHATS:\n  -100".to_string())
+        );
+
+        env.write().unwrap();
+        assert_eq!(fs::read_to_string(&test_file_path).unwrap(), source);
+    }
+
+    #[test]
+    fn test_multi_line_values_round_trip() {
+        let test_file_path = TempLenv::new();
+
+        let mut env = LinoEnv::new(&test_file_path);
+        env.set("QUOTED", "\nleading newline\n");
+        env.set("SCRIPT", "line1\nline2");
+        env.write().unwrap();
+
+        let mut reread = LinoEnv::new(&test_file_path);
+        reread.read().unwrap();
+        assert_eq!(reread.to_hash_map(), env.to_hash_map());
+    }
+}
+
+mod secret_tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_sensitive_keys() {
+        let mut env = LinoEnv::new(TempLenv::new());
+        env.set("GITHUB_TOKEN", "gh_test123");
+        env.set("DB_PASSWORD", "hunter2");
+        env.set("API_URL", "https://example.com");
+
+        let debug = format!("{env:?}");
+        assert!(!debug.contains("gh_test123"));
+        assert!(!debug.contains("hunter2"));
+        assert!(debug.contains("https://example.com"));
+    }
+
+    #[test]
+    fn test_get_secret() {
+        let mut env = LinoEnv::new(TempLenv::new());
+        env.set("PLAIN", "value");
+
+        let secret = env.get_secret("PLAIN").unwrap();
+        assert_eq!(format!("{secret:?}"), "***");
+        assert_eq!(secret.expose(), "value");
+        assert!(env.get_secret("MISSING").is_none());
+    }
+}
+
+mod edge_case_tests {
+    use super::*;
+
+    #[test]
+    fn test_nonexistent_file() {
+        let test_file_path = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file_path);
+        env.read().unwrap();
+
+        assert_eq!(env.get("ANY_KEY"), None);
+        assert!(env.keys().is_empty());
+    }
+
+    #[test]
+    fn test_empty_values() {
+        let test_file_path = TempLenv::new();
+        let mut env = LinoEnv::new(&test_file_path);
+        env.set("EMPTY_KEY", "");
+        env.write().unwrap();
+
+        let mut env2 = LinoEnv::new(&test_file_path);
+        env2.read().unwrap();
+        assert_eq!(env2.get("EMPTY_KEY"), Some(String::new()));
+    }
+}
+
+#[cfg(feature = "embed")]
+mod include_lenv_tests {
+    use crate::{include_lenv, LinoEnv};
+
+    static DEFAULTS: &[(&str, &str)] = include_lenv!("tests/fixtures/embedded.lenv");
+
+    #[test]
+    fn test_static_table() {
+        assert_eq!(
+            DEFAULTS,
+            &[
+                ("API_URL", "https://api.example.com"),
+                ("PORT", "9090"),
+                ("BANNER", "Hello,\nworld"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lino_env() {
+        let env: LinoEnv = include_lenv!("tests/fixtures/embedded.lenv" as LinoEnv);
+        assert_eq!(env.file_path(), "tests/fixtures/embedded.lenv");
+        assert_eq!(env.keys(), vec!["API_URL", "PORT", "BANNER"]);
+        assert_eq!(env.get("PORT"), Some("9090".to_string()));
+    }
+}
+
+mod macros_parser_tests {
+    /// `include_lenv!` parses with a copy of the parser, which must not
+    /// drift from the one `LinoEnv::read` uses.
+    #[test]
+    fn test_macros_parser_is_a_copy() {
+        assert!(
+            include_str!("parser.rs") == include_str!("../macros/src/parser.rs"),
+            "macros/src/parser.rs differs from src/parser.rs; copy it over"
+        );
+    }
+}
+
+mod round_trip_properties {
+    use super::*;
+    use proptest::collection::btree_map;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    /// Any key, with the characters that make keys lossy made likely.
+    fn key() -> impl Strategy<Value = String> {
+        prop_oneof!["(?s).{0,16}", "[a-z #:\\t\\r\\n]{0,8}"]
+    }
+
+    proptest! {
+        #[test]
+        fn read_after_write_returns_the_map(map in btree_map(key(), "(?s).{0,40}", 0..8)) {
+            let file = TempLenv::new();
+            let mut env = LinoEnv::new(&file);
+            for (key, value) in &map {
+                env.set(key, value);
+            }
+
+            let written = env.write();
+            let lossless = map.keys().all(|key| parser::is_lossless_key(key))
+                && map.values().all(|value| parser::is_lossless(value));
+            prop_assert_eq!(written.is_ok(), lossless);
+            if written.is_ok() {
+                let read: BTreeMap<String, String> =
+                    file.read().unwrap().to_hash_map().into_iter().collect();
+                prop_assert_eq!(read, map);
+            }
+        }
+
+        #[test]
+        fn quotes_and_line_breaks_round_trip(value in "[\"' \nab]{0,12}") {
+            let file = TempLenv::new();
+            let mut env = LinoEnv::new(&file);
+            env.set("KEY", &value).set("AFTER", "x");
+            let written = env.write();
+            prop_assert_eq!(written.is_ok(), parser::is_lossless(&value));
+            if written.is_ok() {
+                let read = file.read().unwrap();
+                prop_assert_eq!(read.get("KEY"), Some(value));
+                prop_assert_eq!(read.get("AFTER"), Some("x".to_string()));
+            }
+        }
+    }
+}
+
+mod corpus_tests {
+    use super::*;
+
+    /// Every `tests/corpus/NAME.lenv` is read and written back in place,
+    /// which must produce `NAME.expected` and read back to the same values.
+    #[test]
+    fn test_corpus() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+        let mut inputs: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "lenv")
+            })
+            .collect();
+        inputs.sort();
+        assert!(!inputs.is_empty());
+
+        for input in inputs {
+            let file = TempLenv::new();
+            fs::copy(&input, &file).unwrap();
+            let env = file.read().unwrap();
+            env.write().unwrap();
+
+            let expected = fs::read_to_string(input.with_extension("expected")).unwrap();
+            let written = fs::read_to_string(&file).unwrap();
+            assert_eq!(written, expected, "{}", input.display());
+            assert_eq!(
+                file.read().unwrap().to_hash_map(),
+                env.to_hash_map(),
+                "{}",
+                input.display()
+            );
+        }
+    }
+}
+
+mod conformance_tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// The `.lenv` fixtures shared with the JavaScript implementation.
+    fn fixtures() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../conformance");
+        let mut fixtures: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "lenv")
+            })
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+        fixtures
+    }
+
+    /// `NAME.json` next to a fixture: `[key, value]` pairs in file order.
+    fn expected(fixture: &Path) -> Vec<(String, String)> {
+        let json = fs::read_to_string(fixture.with_extension("json")).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn entries(env: &LinoEnv) -> Vec<(String, String)> {
+        env.keys()
+            .into_iter()
+            .map(|key| {
+                let value = env.get(&key).unwrap_or_default();
+                (key, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read() {
+        for fixture in fixtures() {
+            let env = read_lino_env(fixture.to_str().unwrap()).unwrap();
+            assert_eq!(entries(&env), expected(&fixture), "{}", fixture.display());
+        }
+    }
+
+    #[test]
+    fn test_write_round_trips() {
+        for fixture in fixtures() {
+            let expected = expected(&fixture);
+
+            let file = TempLenv::new();
+            let mut env = LinoEnv::new(&file);
+            for (key, value) in &expected {
+                env.set(key, value);
+            }
+            env.write().unwrap();
+            assert_eq!(
+                entries(&file.read().unwrap()),
+                expected,
+                "{}",
+                fixture.display()
+            );
+
+            // Rewriting in place keeps comments and layout, and the values.
+            let file = TempLenv::new();
+            fs::copy(&fixture, &file).unwrap();
+            file.read().unwrap().write().unwrap();
+            assert_eq!(
+                entries(&file.read().unwrap()),
+                expected,
+                "{}",
+                fixture.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Scoped access to groups of keys.
+//!
+//! Large files group related keys by prefix (`DB_HOST`, `DB_PORT`, ...).
+//! [`LinoEnv::with_prefix`] hands out a [`PrefixView`] of one group with the
+//! prefix stripped, and [`LinoEnv::sections`] groups all keys into a tree.
+//! Views read and write the underlying environment, so changes made through
+//! a view are saved by [`LinoEnv::write`] like any other.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+use crate::LinoEnv;
+
+/// Keys of a [`LinoEnv`] starting with a prefix, accessed without it.
+#[derive(Debug)]
+pub struct PrefixView<'a> {
+    env: &'a mut LinoEnv,
+    prefix: String,
+}
+
+impl PrefixView<'_> {
+    /// The prefix of this view.
+    #[must_use]
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    /// Get the value of `prefix + key`.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.env.get(&self.full_key(key))
+    }
+
+    /// Set `prefix + key` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key policy rejects `prefix + key`, like
+    /// [`LinoEnv::set`].
+    pub fn set(&mut self, key: &str, value: &str) -> &mut Self {
+        let key = self.full_key(key);
+        self.env.set(&key, value);
+        self
+    }
+
+    /// Check if `prefix + key` exists.
+    #[must_use]
+    pub fn has(&self, key: &str) -> bool {
+        self.env.has(&self.full_key(key))
+    }
+
+    /// Delete `prefix + key`.
+    pub fn delete(&mut self, key: &str) -> &mut Self {
+        let key = self.full_key(key);
+        self.env.delete(&key);
+        self
+    }
+
+    /// Keys starting with the prefix, with the prefix stripped, sorted.
+    #[must_use]
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .env
+            .keys()
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(str::to_string))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Keys and values of this view, with the prefix stripped.
+    #[must_use]
+    pub fn to_hash_map(&self) -> HashMap<String, String> {
+        self.keys()
+            .into_iter()
+            .filter_map(|key| Some((key.clone(), self.get(&key)?)))
+            .collect()
+    }
+
+    /// A view of a nested group: `DB_` then `POOL_` scopes to `DB_POOL_`.
+    pub fn with_prefix(&mut self, prefix: &str) -> PrefixView<'_> {
+        let prefix = self.full_key(prefix);
+        self.env.with_prefix(&prefix)
+    }
+
+    /// Write the whole underlying environment to its file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self) -> io::Result<&Self> {
+        self.env.write()?;
+        Ok(self)
+    }
+}
+
+/// A group of keys sharing a prefix, as returned by [`LinoEnv::sections`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Section {
+    /// Full key prefix of this section, including the trailing separator
+    /// (empty for the root).
+    pub prefix: String,
+    /// Value of the key equal to the prefix without its separator, if any.
+    pub value: Option<String>,
+    /// Child sections by name, sorted.
+    pub children: BTreeMap<String, Self>,
+}
+
+impl Section {
+    /// Follow `path` of section names from this section.
+    #[must_use]
+    pub fn get(&self, path: &[&str]) -> Option<&Self> {
+        path.iter()
+            .try_fold(self, |section, name| section.children.get(*name))
+    }
+}
+
+impl LinoEnv {
+    /// A view of the keys starting with `prefix`, with the prefix stripped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set("DB_HOST", "localhost").set("DB_PORT", "5432").set("DEBUG", "true");
+    ///
+    /// let mut db = env.with_prefix("DB_");
+    /// assert_eq!(db.keys(), vec!["HOST", "PORT"]);
+    /// db.set("USER", "app");
+    ///
+    /// assert_eq!(env.get("DB_USER"), Some("app".to_string()));
+    /// ```
+    pub fn with_prefix(&mut self, prefix: &str) -> PrefixView<'_> {
+        PrefixView {
+            env: self,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    /// Group keys into a tree by splitting them on `separator`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use lino_env::LinoEnv;
+    ///
+    /// let mut env = LinoEnv::new(".lenv");
+    /// env.set("DB_HOST", "localhost").set("DB_POOL_SIZE", "10").set("DEBUG", "true");
+    ///
+    /// let root = env.sections("_");
+    /// assert_eq!(root.children.keys().collect::<Vec<_>>(), vec!["DB", "DEBUG"]);
+    /// let pool = root.get(&["DB", "POOL"]).unwrap();
+    /// assert_eq!(pool.prefix, "DB_POOL_");
+    /// assert_eq!(pool.get(&["SIZE"]).unwrap().value.as_deref(), Some("10"));
+    /// ```
+    #[must_use]
+    pub fn sections(&self, separator: &str) -> Section {
+        let mut root = Section::default();
+        for key in self.keys() {
+            let mut section = &mut root;
+            for name in key.split(separator) {
+                let prefix = format!("{}{name}{separator}", section.prefix);
+                section = section
+                    .children
+                    .entry(name.to_string())
+                    .or_insert_with(|| Section {
+                        prefix,
+                        ..Section::default()
+                    });
+            }
+            section.value = self.get(&key);
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempLenv;
+
+    fn env() -> LinoEnv {
+        let mut env = LinoEnv::new("view_test.lenv");
+        env.set("DB_HOST", "localhost")
+            .set("DB_POOL_SIZE", "10")
+            .set("DB", "enabled")
+            .set("DEBUG", "true");
+        env
+    }
+
+    #[test]
+    fn test_prefix_view() {
+        let mut env = env();
+        let mut db = env.with_prefix("DB_");
+        assert_eq!(db.keys(), vec!["HOST", "POOL_SIZE"]);
+        assert!(db.has("HOST"));
+        assert_eq!(db.get("DEBUG"), None);
+
+        db.delete("HOST");
+        db.with_prefix("POOL_").set("SIZE", "20");
+        assert_eq!(
+            db.to_hash_map(),
+            HashMap::from([("POOL_SIZE".to_string(), "20".to_string())])
+        );
+        assert!(!env.has("DB_HOST"));
+        assert_eq!(env.get("DB"), Some("enabled".to_string()));
+    }
+
+    #[test]
+    fn test_sections() {
+        let root = env().sections("_");
+        let db = root.get(&["DB"]).unwrap();
+        assert_eq!(db.value.as_deref(), Some("enabled"));
+        assert_eq!(db.children.keys().collect::<Vec<_>>(), vec!["HOST", "POOL"]);
+        assert_eq!(
+            root.get(&["DB", "POOL", "SIZE"]).unwrap().prefix,
+            "DB_POOL_SIZE_"
+        );
+        assert!(root.get(&["DB", "MISSING"]).is_none());
+    }
+
+    #[test]
+    fn test_view_writes_through() {
+        let file = TempLenv::new();
+        let mut env = LinoEnv::new(&file);
+        env.with_prefix("DB_").set("HOST", "db").write().unwrap();
+
+        let reread = file.read().unwrap();
+        assert_eq!(reread.get("DB_HOST"), Some("db".to_string()));
+    }
+}
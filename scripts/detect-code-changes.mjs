@@ -154,7 +154,8 @@ function isExcludedFromCodeChanges(filePath) {
 }
 
 /**
- * Filter files by language folder
+ * Filter files by language folder; the shared conformance fixtures belong
+ * to every language
  * @param {string[]} files - Array of file paths
  * @param {string} language - 'js', 'rust', or 'all'
  * @returns {string[]} Filtered files
@@ -165,7 +166,9 @@ function filterByLanguage(files, language) {
   }
 
   const prefix = language === 'js' ? 'js/' : 'rust/';
-  return files.filter((file) => file.startsWith(prefix));
+  return files.filter(
+    (file) => file.startsWith(prefix) || file.startsWith('conformance/')
+  );
 }
 
 /**
@@ -231,10 +234,10 @@ function detectChanges() {
   // Check if any code files changed
   const codePattern =
     lang === 'rust'
-      ? /\.(rs|toml|mjs|js|yml|yaml)$|\.github\/workflows\//
+      ? /\.(rs|toml|mjs|js|yml|yaml)$|\.github\/workflows\/|^conformance\//
       : lang === 'js'
-        ? /\.(mjs|js|json|yml|yaml)$|\.github\/workflows\//
-        : /\.(mjs|js|json|rs|toml|yml|yaml)$|\.github\/workflows\//;
+        ? /\.(mjs|js|json|yml|yaml)$|\.github\/workflows\/|^conformance\//
+        : /\.(mjs|js|json|rs|toml|yml|yaml)$|\.github\/workflows\/|^conformance\//;
   const codeChanged = codeChangedFiles.some((file) => codePattern.test(file));
   setOutput('any-code-changed', codeChanged ? 'true' : 'false');
 